@@ -3,7 +3,10 @@ use mockable::{Command, CommandRunner, DefaultCommandRunner};
 async fn echo(msg: &str, runner: &dyn CommandRunner) -> String {
     let cmd = Command::new("echo").with_arg(msg);
     let output = runner.run(&cmd).await.expect("echo failed");
-    String::from_utf8(output.stdout).expect("echo output is not utf8")
+    output
+        .stdout_utf8()
+        .expect("echo output is not utf8")
+        .to_string()
 }
 
 #[tokio::main]
@@ -27,6 +30,7 @@ mod test {
         runner.expect_run().with(eq(cmd)).returning(|_| {
             Ok(CommandOutput {
                 code: Some(0),
+                signal: None,
                 stderr: vec![],
                 stdout: expected.as_bytes().to_vec(),
             })
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use mockable::{DefaultDownloader, DefaultHttpClient, DownloadOptions, Downloader};
+
+async fn fetch(downloader: &dyn Downloader, url: &str, dest: &Path) -> u64 {
+    downloader
+        .download(url, dest, DownloadOptions::new())
+        .await
+        .unwrap_or_default()
+}
+
+#[tokio::main]
+async fn main() {
+    let downloader = DefaultDownloader::new(DefaultHttpClient::new());
+    let dest = std::env::temp_dir().join("rust-lang.html");
+    let size = fetch(&downloader, "https://www.rust-lang.org", &dest).await;
+    println!("{size}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::MockDownloader;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test() {
+        let mut downloader = MockDownloader::new();
+        downloader.expect_download().returning(|_, _, _| Ok(5));
+        let size = fetch(
+            &downloader,
+            "https://www.rust-lang.org",
+            Path::new("index.html"),
+        )
+        .await;
+        assert_eq!(size, 5);
+    }
+}
@@ -22,13 +22,7 @@ mod test {
 
     #[tokio::test]
     async fn test() {
-        let expected = HttpRequest {
-            body: vec![],
-            headers: Default::default(),
-            method: "GET".into(),
-            path: "/".into(),
-            query: Default::default(),
-        };
+        let expected = HttpRequest::new("GET", "/");
         let mut server = MockHttpServer::new();
         server.expect_next().return_const(expected.clone());
         server.expect_stop().return_const(());
@@ -0,0 +1,32 @@
+use mockable::{DefaultHttpClient, HttpClient, HttpClientRequest};
+
+async fn status(client: &dyn HttpClient, url: &str) -> u16 {
+    client
+        .send(HttpClientRequest::get(url))
+        .await
+        .map(|resp| resp.status)
+        .unwrap_or_default()
+}
+
+#[tokio::main]
+async fn main() {
+    let status = status(&DefaultHttpClient::new(), "https://www.rust-lang.org").await;
+    println!("{status}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::{HttpClientResponse, MockHttpClient};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test() {
+        let mut client = MockHttpClient::new();
+        client
+            .expect_send()
+            .returning(|_| Ok(HttpClientResponse::new(200)));
+        let status = status(&client, "https://www.rust-lang.org").await;
+        assert_eq!(status, 200);
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use mockable::{DefaultClock, KeyValueStore, MemoryKeyValueStore};
+
+async fn cache_session(store: &dyn KeyValueStore, user: &str, token: &str) -> bool {
+    store
+        .put(
+            &format!("session:{user}"),
+            token.as_bytes().to_vec(),
+            Some(Duration::from_secs(3600)),
+        )
+        .await
+        .is_ok()
+}
+
+#[tokio::main]
+async fn main() {
+    let store = MemoryKeyValueStore::new(DefaultClock);
+    let cached = cache_session(&store, "alice", "token").await;
+    println!("{cached}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::MockKeyValueStore;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test() {
+        let mut store = MockKeyValueStore::new();
+        store
+            .expect_put()
+            .withf(|key, val, ttl| {
+                key == "session:alice" && val == b"token" && *ttl == Some(Duration::from_secs(3600))
+            })
+            .returning(|_, _, _| Ok(()));
+        let cached = cache_session(&store, "alice", "token").await;
+        assert!(cached);
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use mockable::{Clock, DefaultClock, DefaultSchedule, Schedule};
+
+fn next_backup(schedule: &dyn Schedule, clock: &dyn Clock) -> Option<DateTime<Utc>> {
+    schedule.next_occurrence(clock.utc())
+}
+
+fn main() {
+    let schedule = DefaultSchedule::new("0 0 3 * * * *").expect("invalid cron expression");
+    let next = next_backup(&schedule, &DefaultClock);
+    println!("{next:?}");
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use mockable::{MockClock, MockSchedule};
+    use mockall::predicate::eq;
+
+    use super::*;
+
+    #[test]
+    fn test() {
+        let now = Utc::now();
+        let expected = now + Duration::hours(1);
+        let mut clock = MockClock::new();
+        clock.expect_utc().return_const(now);
+        let mut schedule = MockSchedule::new();
+        schedule
+            .expect_next_occurrence()
+            .with(eq(now))
+            .return_const(Some(expected));
+        let next = next_backup(&schedule, &clock);
+        assert_eq!(next, Some(expected));
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use mockable::{DefaultClock, DefaultScheduler, DefaultSleeper, Scheduler, TaskHandle};
+
+fn start_heartbeat(scheduler: &dyn Scheduler, beats: Arc<AtomicUsize>) -> TaskHandle {
+    scheduler.schedule_every(
+        Duration::from_secs(1),
+        Box::new(move || {
+            let beats = beats.clone();
+            Box::pin(async move {
+                beats.fetch_add(1, Ordering::Relaxed);
+            })
+        }),
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    let scheduler = DefaultScheduler::new(DefaultClock, DefaultSleeper);
+    let beats = Arc::new(AtomicUsize::new(0));
+    let handle = start_heartbeat(&scheduler, beats.clone());
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+    handle.cancel();
+    println!("{}", beats.load(Ordering::Relaxed));
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use mockable::{FakeClock, FakeScheduler};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test() {
+        let scheduler = FakeScheduler::new(FakeClock::new(Utc::now()));
+        let beats = Arc::new(AtomicUsize::new(0));
+        let handle = start_heartbeat(&scheduler, beats.clone());
+        scheduler.advance(Duration::from_secs(3)).await;
+        handle.cancel();
+        scheduler.advance(Duration::from_secs(3)).await;
+        assert_eq!(beats.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,25 @@
+use mockable::{DefaultShortIdGenerator, ShortIdGenerator};
+
+fn invite_link(gen: &dyn ShortIdGenerator) -> String {
+    format!("https://example.com/invite/{}", gen.generate())
+}
+
+fn main() {
+    let gen = DefaultShortIdGenerator::new().with_length(8);
+    println!("{}", invite_link(&gen));
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::MockShortIdGenerator;
+
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut gen = MockShortIdGenerator::new();
+        gen.expect_generate().returning(|| "Xk3P9aQz".into());
+        let link = invite_link(&gen);
+        assert_eq!(link, "https://example.com/invite/Xk3P9aQz");
+    }
+}
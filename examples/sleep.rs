@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use mockable::{Command, CommandRunner, DefaultCommandRunner, DefaultSleeper, Sleeper};
+
+async fn fetch(runner: &dyn CommandRunner, sleeper: &dyn Sleeper) -> bool {
+    let cmd = Command::new("git").with_arg("fetch");
+    for attempt in 1..=3 {
+        let output = runner.run(&cmd).await.expect("failed to run git");
+        if output.success() {
+            return true;
+        }
+        sleeper.sleep(Duration::from_secs(attempt)).await;
+    }
+    false
+}
+
+#[tokio::main]
+async fn main() {
+    let fetched = fetch(&DefaultCommandRunner, &DefaultSleeper).await;
+    println!("{fetched}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::{CommandMatcher, CommandOutput, FakeCommandRunner, FakeSleeper};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test() {
+        let runner = FakeCommandRunner::new().with_output(
+            CommandMatcher::new("git"),
+            CommandOutput {
+                code: Some(1),
+                signal: None,
+                stderr: vec![],
+                stdout: vec![],
+            },
+        );
+        let sleeper = FakeSleeper::new();
+        let fetched = fetch(&runner, &sleeper).await;
+        assert!(!fetched);
+        assert_eq!(
+            sleeper.sleeps(),
+            vec![
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(3)
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use mockable::{DefaultSqliteClient, SqliteClient, SqliteValue};
+
+fn user_names(client: &dyn SqliteClient) -> Vec<String> {
+    client
+        .query("SELECT name FROM user ORDER BY name", &[])
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|row| {
+            row.get("name")
+                .and_then(SqliteValue::as_str)
+                .map(String::from)
+        })
+        .collect()
+}
+
+fn main() {
+    let client = DefaultSqliteClient::open_in_memory().expect("failed to open database");
+    client
+        .transaction(&mut |tx| {
+            tx.execute("CREATE TABLE user (name TEXT)", &[])?;
+            tx.execute("INSERT INTO user (name) VALUES (?1)", &["alice".into()])?;
+            Ok(())
+        })
+        .expect("failed to initialize database");
+    let names = user_names(&client);
+    println!("{names:?}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::{MockSqliteClient, SqliteRow};
+
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut client = MockSqliteClient::new();
+        client
+            .expect_query()
+            .returning(|_, _| Ok(vec![SqliteRow::new().with_column("name", "alice")]));
+        let names = user_names(&client);
+        assert_eq!(names, vec!["alice"]);
+    }
+}
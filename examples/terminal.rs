@@ -0,0 +1,29 @@
+use mockable::{DefaultTerminal, Terminal};
+
+fn delete_account(term: &dyn Terminal, user: &str) -> bool {
+    term.confirm(&format!("Delete the account of {user}?"))
+        .unwrap_or_default()
+}
+
+fn main() {
+    let deleted = delete_account(&DefaultTerminal, "alice");
+    println!("{deleted}");
+}
+
+#[cfg(test)]
+mod test {
+    use mockable::MockTerminal;
+    use mockall::predicate::eq;
+
+    use super::*;
+
+    #[test]
+    fn test() {
+        let mut term = MockTerminal::new();
+        term.expect_confirm()
+            .with(eq("Delete the account of alice?"))
+            .returning(|_| Ok(true));
+        let deleted = delete_account(&term, "alice");
+        assert!(deleted);
+    }
+}
@@ -1,4 +1,18 @@
-use chrono::{DateTime, Local, Utc};
+#[cfg(feature = "mock")]
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError,
+};
+use std::{
+    ops::{Add, Sub},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, DurationRound, FixedOffset, Local, Offset, TimeZone, Utc};
+#[cfg(feature = "tz")]
+use chrono_tz::Tz;
+#[cfg(feature = "time")]
+use time::{error::IndeterminateOffset, OffsetDateTime, UtcOffset};
 
 // Clock
 
@@ -8,9 +22,70 @@ use chrono::{DateTime, Local, Utc};
 ///
 /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/clock.rs).
 pub trait Clock: Send + Sync {
+    /// Returns the current monotonic time.
+    ///
+    /// Unlike the other methods, it is suited to measure elapsed time.
+    ///
+    /// By default, the monotonic time of the system is returned.
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
     /// Returns the current time in the local timezone.
     fn local(&self) -> DateTime<Local>;
 
+    /// Returns the local timezone.
+    ///
+    /// By default, the name is detected from the system and the offset is the one of
+    /// [`local`](#tymethod.local).
+    fn local_timezone(&self) -> LocalTimezone {
+        LocalTimezone {
+            name: iana_time_zone::get_timezone().ok(),
+            offset: self.local().offset().fix(),
+        }
+    }
+
+    /// Returns the current time in the given timezone.
+    ///
+    /// By default, it is computed from [`utc`](#tymethod.utc).
+    ///
+    /// **This is supported on `feature=tz` only.**
+    #[cfg(feature = "tz")]
+    fn now_in(&self, tz: Tz) -> DateTime<Tz> {
+        self.utc().with_timezone(&tz)
+    }
+
+    /// Returns the current time in the local timezone as a `time` value.
+    ///
+    /// If the local offset can't be determined, an error is returned.
+    ///
+    /// By default, it is computed from [`local`](#tymethod.local) and never fails.
+    ///
+    /// **This is supported on `feature=time` only.**
+    #[cfg(feature = "time")]
+    fn odt_local(&self) -> Result<OffsetDateTime, IndeterminateOffset> {
+        Ok(odt_local(self.local()))
+    }
+
+    /// Returns the current time in UTC as a `time` value.
+    ///
+    /// By default, it is computed from [`system_time`](#method.system_time).
+    ///
+    /// **This is supported on `feature=time` only.**
+    #[cfg(feature = "time")]
+    fn odt_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::from(self.system_time())
+    }
+
+    /// Returns the current system time.
+    ///
+    /// It is suited to be compared with filesystem timestamps.
+    ///
+    /// By default, it is computed from [`utc`](#tymethod.utc).
+    fn system_time(&self) -> SystemTime {
+        self.utc().into()
+    }
+
     /// Returns the current time in UTC.
     fn utc(&self) -> DateTime<Utc>;
 }
@@ -25,15 +100,388 @@ pub trait Clock: Send + Sync {
 pub struct DefaultClock;
 
 impl Clock for DefaultClock {
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
     fn local(&self) -> DateTime<Local> {
         Local::now()
     }
 
+    #[cfg(feature = "time")]
+    fn odt_local(&self) -> Result<OffsetDateTime, IndeterminateOffset> {
+        OffsetDateTime::now_local()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
     fn utc(&self) -> DateTime<Utc> {
         Utc::now()
     }
 }
 
+// FakeClock
+
+/// Implementation of [`Clock`](trait.Clock.html) whose time only changes when it is advanced.
+///
+/// Clones share the same time.
+///
+/// **This is supported on `feature=clock,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/clock.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct FakeClock(Arc<Mutex<FakeClockState>>);
+
+#[cfg(feature = "mock")]
+impl FakeClock {
+    /// Creates a new clock frozen at the given time.
+    pub fn new(utc: DateTime<Utc>) -> Self {
+        Self(Arc::new(Mutex::new(FakeClockState {
+            instant: Instant::now(),
+            timezone: None,
+            utc,
+        })))
+    }
+
+    /// Advances the time by `duration`.
+    ///
+    /// # Panics
+    /// Panics if the time overflows.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state();
+        state.instant += duration;
+        state.utc += chrono::Duration::from_std(duration).expect("duration is out of range");
+    }
+
+    /// Sets the time.
+    ///
+    /// The monotonic time is advanced by the difference if the time is in the future, it is left
+    /// unchanged otherwise.
+    pub fn set(&self, utc: DateTime<Utc>) {
+        let mut state = self.state();
+        if let Ok(duration) = (utc - state.utc).to_std() {
+            state.instant += duration;
+        }
+        state.utc = utc;
+    }
+
+    /// Sets the local timezone.
+    ///
+    /// Its offset is applied to the local times returned by the clock.
+    ///
+    /// By default, the name is detected from the system and the offset is the one of the system
+    /// timezone at the current time of the clock.
+    pub fn set_local_timezone(&self, timezone: LocalTimezone) {
+        self.state().timezone = Some(timezone);
+    }
+
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, FakeClockState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Clock for FakeClock {
+    fn instant(&self) -> Instant {
+        self.state().instant
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        let state = self.state();
+        match &state.timezone {
+            Some(timezone) => {
+                DateTime::from_naive_utc_and_offset(state.utc.naive_utc(), timezone.offset)
+            }
+            None => state.utc.with_timezone(&Local),
+        }
+    }
+
+    fn local_timezone(&self) -> LocalTimezone {
+        let state = self.state();
+        state
+            .timezone
+            .clone()
+            .unwrap_or_else(|| local_timezone(state.utc))
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.state().utc.into()
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        self.state().utc
+    }
+}
+
+// FakeClockState
+
+#[cfg(feature = "mock")]
+struct FakeClockState {
+    instant: Instant,
+    timezone: Option<LocalTimezone>,
+    utc: DateTime<Utc>,
+}
+
+// LocalTimezone
+
+/// The local timezone.
+///
+/// **This is supported on `feature=clock` only.**
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocalTimezone {
+    /// The IANA name (like `Europe/Paris`), if it can be determined.
+    pub name: Option<String>,
+    /// The current offset from UTC.
+    pub offset: FixedOffset,
+}
+
+// OffsetClock
+
+/// Implementation of [`Clock`](trait.Clock.html) that shifts the time of an inner clock by a fixed
+/// offset.
+///
+/// The offset can be negative.
+///
+/// **This is supported on `feature=clock` only.**
+pub struct OffsetClock<CLOCK: Clock> {
+    inner: CLOCK,
+    offset: chrono::Duration,
+}
+
+impl<CLOCK: Clock> OffsetClock<CLOCK> {
+    /// Creates a new clock that shifts the time of `inner` by `offset`.
+    pub fn new(inner: CLOCK, offset: chrono::Duration) -> Self {
+        Self { inner, offset }
+    }
+
+    #[inline]
+    fn shift<T: Add<Duration, Output = T> + Sub<Duration, Output = T>>(&self, time: T) -> T {
+        match self.offset.to_std() {
+            Ok(offset) => time + offset,
+            Err(_) => time - (-self.offset).to_std().unwrap_or_default(),
+        }
+    }
+}
+
+impl<CLOCK: Clock> Clock for OffsetClock<CLOCK> {
+    fn instant(&self) -> Instant {
+        self.shift(self.inner.instant())
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        self.inner.local() + self.offset
+    }
+
+    fn local_timezone(&self) -> LocalTimezone {
+        self.inner.local_timezone()
+    }
+
+    #[cfg(feature = "tz")]
+    fn now_in(&self, tz: Tz) -> DateTime<Tz> {
+        self.inner.now_in(tz) + self.offset
+    }
+
+    #[cfg(feature = "time")]
+    fn odt_local(&self) -> Result<OffsetDateTime, IndeterminateOffset> {
+        self.inner.odt_local().map(|time| self.shift(time))
+    }
+
+    #[cfg(feature = "time")]
+    fn odt_utc(&self) -> OffsetDateTime {
+        self.shift(self.inner.odt_utc())
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.shift(self.inner.system_time())
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        self.inner.utc() + self.offset
+    }
+}
+
+// QuantizedClock
+
+/// Implementation of [`Clock`](trait.Clock.html) that rounds the times of an inner clock down to a
+/// resolution.
+///
+/// `chrono` times are rounded in their own timezone, the other ones relatively to the UNIX epoch.
+/// The monotonic time is left unchanged.
+///
+/// **This is supported on `feature=clock` only.**
+pub struct QuantizedClock<CLOCK: Clock> {
+    inner: CLOCK,
+    resolution: Duration,
+}
+
+impl<CLOCK: Clock> QuantizedClock<CLOCK> {
+    /// Creates a new clock that rounds the times of `inner` down to `resolution`.
+    ///
+    /// # Panics
+    /// Panics if `resolution` is zero.
+    pub fn new(inner: CLOCK, resolution: Duration) -> Self {
+        assert!(!resolution.is_zero(), "resolution must not be zero");
+        Self { inner, resolution }
+    }
+
+    #[inline]
+    fn trunc<TZ: TimeZone>(&self, time: DateTime<TZ>) -> DateTime<TZ> {
+        match chrono::Duration::from_std(self.resolution) {
+            Ok(resolution) => time.clone().duration_trunc(resolution).unwrap_or(time),
+            Err(_) => time,
+        }
+    }
+
+    #[cfg(feature = "time")]
+    #[inline]
+    fn trunc_odt(&self, time: OffsetDateTime) -> OffsetDateTime {
+        let rem = time
+            .unix_timestamp_nanos()
+            .rem_euclid(self.resolution.as_nanos() as i128);
+        time - Duration::from_nanos(rem as u64)
+    }
+}
+
+impl<CLOCK: Clock> Clock for QuantizedClock<CLOCK> {
+    fn instant(&self) -> Instant {
+        self.inner.instant()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        self.trunc(self.inner.local())
+    }
+
+    fn local_timezone(&self) -> LocalTimezone {
+        self.inner.local_timezone()
+    }
+
+    #[cfg(feature = "tz")]
+    fn now_in(&self, tz: Tz) -> DateTime<Tz> {
+        self.trunc(self.inner.now_in(tz))
+    }
+
+    #[cfg(feature = "time")]
+    fn odt_local(&self) -> Result<OffsetDateTime, IndeterminateOffset> {
+        self.inner.odt_local().map(|time| self.trunc_odt(time))
+    }
+
+    #[cfg(feature = "time")]
+    fn odt_utc(&self) -> OffsetDateTime {
+        self.trunc_odt(self.inner.odt_utc())
+    }
+
+    fn system_time(&self) -> SystemTime {
+        let time = self.inner.system_time();
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => {
+                let rem = since.as_nanos() % self.resolution.as_nanos();
+                time - Duration::from_nanos(rem as u64)
+            }
+            Err(_) => time,
+        }
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        self.trunc(self.inner.utc())
+    }
+}
+
+// SequenceClock
+
+/// Implementation of [`Clock`](trait.Clock.html) that returns a predefined sequence of times.
+///
+/// Each call to any method returns the next time of the sequence. The monotonic time is derived
+/// from the elapsed time since the first one.
+///
+/// Clones share the same sequence.
+///
+/// **This is supported on `feature=clock,mock` only.**
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct SequenceClock(Arc<SequenceClockState>);
+
+#[cfg(feature = "mock")]
+impl SequenceClock {
+    /// Creates a new clock returning the given times.
+    ///
+    /// When the sequence is exhausted, the clock panics.
+    pub fn new(times: Vec<DateTime<Utc>>) -> Self {
+        Self(Arc::new(SequenceClockState {
+            idx: AtomicUsize::new(0),
+            instant: Instant::now(),
+            repeat_last: false,
+            times,
+        }))
+    }
+
+    /// Creates a new clock returning the given times.
+    ///
+    /// When the sequence is exhausted, the last time is repeated.
+    pub fn repeating_last(times: Vec<DateTime<Utc>>) -> Self {
+        Self(Arc::new(SequenceClockState {
+            idx: AtomicUsize::new(0),
+            instant: Instant::now(),
+            repeat_last: true,
+            times,
+        }))
+    }
+
+    /// Returns the number of times the clock has been called.
+    pub fn count(&self) -> usize {
+        self.0.idx.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn next(&self) -> DateTime<Utc> {
+        let idx = self.0.idx.fetch_add(1, Ordering::Relaxed);
+        match self.0.times.get(idx) {
+            Some(time) => *time,
+            None => match self.0.times.last() {
+                Some(time) if self.0.repeat_last => *time,
+                _ => panic!("SequenceClock called more times than expected"),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Clock for SequenceClock {
+    fn instant(&self) -> Instant {
+        let elapsed = self.next() - self.0.times[0];
+        self.0.instant + elapsed.to_std().unwrap_or_default()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        self.next().with_timezone(&Local)
+    }
+
+    fn local_timezone(&self) -> LocalTimezone {
+        local_timezone(self.next())
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.next().into()
+    }
+
+    fn utc(&self) -> DateTime<Utc> {
+        self.next()
+    }
+}
+
+// SequenceClockState
+
+#[cfg(feature = "mock")]
+struct SequenceClockState {
+    idx: AtomicUsize,
+    instant: Instant,
+    repeat_last: bool,
+    times: Vec<DateTime<Utc>>,
+}
+
 // MockClock
 
 #[cfg(feature = "mock")]
@@ -46,7 +494,217 @@ mockall::mock! {
     pub Clock {}
 
     impl Clock for Clock {
+        fn instant(&self) -> Instant;
         fn local(&self) -> DateTime<Local>;
+        fn local_timezone(&self) -> LocalTimezone;
+        #[cfg(feature = "tz")]
+        fn now_in(&self, tz: Tz) -> DateTime<Tz>;
+        #[cfg(feature = "time")]
+        fn odt_local(&self) -> Result<OffsetDateTime, IndeterminateOffset>;
+        #[cfg(feature = "time")]
+        fn odt_utc(&self) -> OffsetDateTime;
+        fn system_time(&self) -> SystemTime;
         fn utc(&self) -> DateTime<Utc>;
     }
 }
+
+// Functions
+
+#[cfg(feature = "mock")]
+#[inline]
+fn local_timezone(utc: DateTime<Utc>) -> LocalTimezone {
+    LocalTimezone {
+        name: iana_time_zone::get_timezone().ok(),
+        offset: utc.with_timezone(&Local).offset().fix(),
+    }
+}
+
+#[cfg(feature = "time")]
+#[inline]
+fn odt_local(local: DateTime<Local>) -> OffsetDateTime {
+    let offset = local.offset().local_minus_utc();
+    let offset = UtcOffset::from_whole_seconds(offset).expect("local offset is out of range");
+    OffsetDateTime::from(SystemTime::from(local)).to_offset(offset)
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod clock {
+        use super::*;
+
+        // UtcClock
+
+        struct UtcClock(DateTime<Utc>);
+
+        impl Clock for UtcClock {
+            fn local(&self) -> DateTime<Local> {
+                self.0.with_timezone(&Local)
+            }
+
+            fn utc(&self) -> DateTime<Utc> {
+                self.0
+            }
+        }
+
+        // Tests
+
+        #[test]
+        fn local_timezone() {
+            let clock = UtcClock(Utc::now());
+            assert_eq!(clock.local_timezone().offset, clock.local().offset().fix());
+        }
+
+        #[cfg(feature = "tz")]
+        #[test]
+        fn now_in() {
+            let utc = Utc::now();
+            let clock = UtcClock(utc);
+            let time = clock.now_in(chrono_tz::Asia::Tokyo);
+            assert_eq!(time, utc);
+            assert_eq!(time.timezone(), chrono_tz::Asia::Tokyo);
+        }
+
+        #[cfg(feature = "time")]
+        #[test]
+        fn odt() {
+            let utc = Utc::now();
+            let clock = UtcClock(utc);
+            let time = clock.odt_utc();
+            assert_eq!(time, OffsetDateTime::from(SystemTime::from(utc)));
+            let local = clock.odt_local().expect("failed to get local time");
+            assert_eq!(local, time);
+            assert_eq!(
+                local.offset().whole_seconds(),
+                clock.local().offset().local_minus_utc()
+            );
+        }
+
+        #[test]
+        fn system_time() {
+            let utc = Utc::now();
+            let clock = UtcClock(utc);
+            assert_eq!(clock.system_time(), SystemTime::from(utc));
+        }
+    }
+
+    mod fake_clock {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn advance() {
+            let start = Utc::now();
+            let clock = FakeClock::new(start);
+            let instant = clock.instant();
+            clock.clone().advance(Duration::from_secs(90));
+            assert_eq!(clock.utc(), start + chrono::Duration::seconds(90));
+            assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+        }
+
+        #[test]
+        fn set() {
+            let start = Utc::now();
+            let clock = FakeClock::new(start);
+            let instant = clock.instant();
+            clock.set(start + chrono::Duration::seconds(10));
+            assert_eq!(clock.instant() - instant, Duration::from_secs(10));
+            clock.set(start - chrono::Duration::seconds(10));
+            assert_eq!(clock.utc(), start - chrono::Duration::seconds(10));
+            assert_eq!(clock.instant() - instant, Duration::from_secs(10));
+        }
+
+        #[test]
+        fn set_local_timezone() {
+            let clock = FakeClock::new(Utc::now());
+            let timezone = LocalTimezone {
+                name: Some("Europe/Paris".into()),
+                offset: FixedOffset::east_opt(7200).expect("failed to create offset"),
+            };
+            clock.set_local_timezone(timezone.clone());
+            assert_eq!(clock.local_timezone(), timezone);
+            assert_eq!(clock.local().offset().fix(), timezone.offset);
+            assert_eq!(clock.local().naive_utc(), clock.utc().naive_utc());
+            #[cfg(feature = "time")]
+            {
+                let time = clock.odt_local().expect("failed to get local time");
+                assert_eq!(time.offset().whole_seconds(), 7200);
+                assert_eq!(time, clock.odt_utc());
+            }
+        }
+    }
+
+    mod offset_clock {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn negative() {
+            let now = Utc::now();
+            let inner = FakeClock::new(now);
+            let instant = inner.instant();
+            let clock = OffsetClock::new(inner, chrono::Duration::seconds(-30));
+            assert_eq!(clock.utc(), now - chrono::Duration::seconds(30));
+            assert_eq!(instant - clock.instant(), Duration::from_secs(30));
+        }
+
+        #[test]
+        fn positive() {
+            let now = Utc::now();
+            let inner = FakeClock::new(now);
+            let instant = inner.instant();
+            let clock = OffsetClock::new(inner, chrono::Duration::seconds(30));
+            assert_eq!(clock.utc(), now + chrono::Duration::seconds(30));
+            assert_eq!(clock.instant() - instant, Duration::from_secs(30));
+        }
+    }
+
+    mod quantized_clock {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn test() {
+            let time = Utc.with_ymd_and_hms(2023, 10, 1, 12, 34, 56).unwrap()
+                + chrono::Duration::nanoseconds(789);
+            let expected = Utc.with_ymd_and_hms(2023, 10, 1, 12, 34, 0).unwrap();
+            let clock = QuantizedClock::new(FakeClock::new(time), Duration::from_secs(60));
+            assert_eq!(clock.utc(), expected);
+            assert_eq!(clock.local(), expected);
+            assert_eq!(clock.system_time(), SystemTime::from(expected));
+        }
+    }
+
+    mod sequence_clock {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        #[should_panic]
+        fn exhausted() {
+            let clock = SequenceClock::new(vec![Utc::now()]);
+            clock.utc();
+            clock.utc();
+        }
+
+        #[test]
+        fn repeating_last() {
+            let start = Utc::now();
+            let end = start + chrono::Duration::seconds(5);
+            let clock = SequenceClock::repeating_last(vec![start, end]);
+            let instant = clock.instant();
+            assert_eq!(clock.instant() - instant, Duration::from_secs(5));
+            assert_eq!(clock.utc(), end);
+            assert_eq!(clock.count(), 3);
+        }
+    }
+}
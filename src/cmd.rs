@@ -1,24 +1,118 @@
-use std::{collections::HashMap, io::Result, path::PathBuf, process::Output};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    ffi::OsString,
+    fmt::{self, Debug, Display, Formatter},
+    fs::File,
+    io::{self, Result},
+    path::PathBuf,
+    process::{Output, Stdio},
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "cmd-tracing")]
+use std::future::Future;
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+#[cfg(feature = "mock")]
+use std::sync::{Mutex, PoisonError};
 
 use async_trait::async_trait;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    process::Child,
+    sync::Semaphore,
+    time::sleep,
+};
+use tracing::warn;
+#[cfg(feature = "cmd-tracing")]
+use tracing::{debug, field, info_span, Instrument};
+
+#[cfg(feature = "mock")]
+type ArgsPredicate = dyn Fn(&[OsString]) -> bool + Send + Sync;
+type RetryPredicate = dyn Fn(&Result<CommandOutput>) -> bool + Send + Sync;
+type RunHook = dyn Fn(&Command, &Result<CommandOutput>, Duration) + Send + Sync;
+
+#[cfg(feature = "cmd-tracing")]
+const TRACED_ARGS_MAX_LEN: usize = 256;
+
 // Command
 
 /// A command.
 ///
+/// The values of the environment variables are redacted when the command is formatted.
+///
 /// **This is supported on `feature=cmd` only.**
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct Command {
     /// The arguments to pass to the command.
-    pub args: Vec<String>,
+    ///
+    /// They are serialized as strings, invalid UTF-8 sequences being replaced with `U+FFFD`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "deserialize_os_strings",
+            serialize_with = "serialize_os_strings"
+        )
+    )]
+    pub args: Vec<OsString>,
     /// The current working directory to run the command in.
     pub cwd: Option<PathBuf>,
     /// The environment variables to set for the command.
     pub env: Option<HashMap<String, String>>,
+    /// Whether the command should not inherit the environment of the current process.
+    pub env_clear: bool,
+    /// The environment variables to not inherit from the current process.
+    pub env_remove: HashSet<String>,
     /// The group to run the command as.
     #[cfg(unix)]
     pub gid: Option<u32>,
+    /// Whether the command should be killed when its [`ChildProcess`](trait.ChildProcess.html)
+    /// is dropped.
+    pub kill_on_drop: bool,
+    /// The resource limits of the command.
+    #[cfg(unix)]
+    pub limits: ResourceLimits,
+    /// Whether the command should be run in a new session.
+    #[cfg(unix)]
+    pub new_session: bool,
+    /// The niceness of the command.
+    #[cfg(unix)]
+    pub nice: Option<i32>,
+    /// The process group to run the command in.
+    ///
+    /// `0` means that a new process group is created with the command as leader.
+    #[cfg(unix)]
+    pub process_group: Option<i32>,
     /// The program to run.
-    pub program: String,
+    ///
+    /// It is serialized as a string, invalid UTF-8 sequences being replaced with `U+FFFD`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "deserialize_os_string",
+            serialize_with = "serialize_os_string"
+        )
+    )]
+    pub program: OsString,
+    /// Whether the arguments are passed to the program verbatim.
+    ///
+    /// On Windows, arguments are otherwise quoted according to the MSVC rules, which `cmd` doesn't
+    /// follow. It has no effect on other platforms.
+    pub raw_args: bool,
+    /// What to do with the standard error of the command.
+    pub stderr: CommandStdio,
+    /// What to do with the standard output of the command.
+    pub stdout: CommandStdio,
     /// The user to run the command as.
     #[cfg(unix)]
     pub uid: Option<u32>,
@@ -26,28 +120,69 @@ pub struct Command {
 
 impl Command {
     /// Creates a new command.
-    pub fn new<S: Into<String>>(program: S) -> Self {
+    pub fn new<S: Into<OsString>>(program: S) -> Self {
         Self {
             args: vec![],
             cwd: None,
             env: None,
+            env_clear: false,
+            env_remove: HashSet::new(),
             #[cfg(unix)]
             gid: None,
+            kill_on_drop: false,
+            #[cfg(unix)]
+            limits: ResourceLimits::default(),
+            #[cfg(unix)]
+            new_session: false,
+            #[cfg(unix)]
+            nice: None,
+            #[cfg(unix)]
+            process_group: None,
             program: program.into(),
+            raw_args: false,
+            stderr: CommandStdio::Piped,
+            stdout: CommandStdio::Piped,
             #[cfg(unix)]
             uid: None,
         }
     }
 
+    /// Parses a command from a string using shell quoting rules.
+    ///
+    /// The first word is the program and the following ones are the arguments. No shell feature
+    /// other than quoting and escaping is supported.
+    pub fn parse(s: &str) -> std::result::Result<Self, CommandParseError> {
+        let mut words = shell_words::split(s)
+            .map_err(CommandParseError::Invalid)?
+            .into_iter();
+        let program = words.next().ok_or(CommandParseError::Empty)?;
+        Ok(Self::new(program).with_args(words))
+    }
+
+    /// Creates a new command running the given script through the system shell.
+    ///
+    /// The script is run with `sh -c` on unix and with `cmd /C` on Windows. It is passed verbatim,
+    /// see [`raw_args`](#structfield.raw_args).
+    pub fn shell<S: Into<OsString>>(script: S) -> Self {
+        #[cfg(windows)]
+        let (program, flag) = ("cmd", "/C");
+        #[cfg(not(windows))]
+        let (program, flag) = ("sh", "-c");
+        Self::new(program)
+            .with_arg(flag)
+            .with_arg(script)
+            .with_raw_args()
+    }
+
     /// Add argument.
-    pub fn with_arg<S: Into<String>>(mut self, arg: S) -> Self {
+    pub fn with_arg<S: Into<OsString>>(mut self, arg: S) -> Self {
         self.args.push(arg.into());
         self
     }
 
     /// Set arguments.
-    pub fn with_args(mut self, args: Vec<String>) -> Self {
-        self.args = args;
+    pub fn with_args<S: Into<OsString>, I: IntoIterator<Item = S>>(mut self, args: I) -> Self {
+        self.args = args.into_iter().map(|arg| arg.into()).collect();
         self
     }
 
@@ -70,6 +205,26 @@ impl Command {
         self
     }
 
+    /// Do not inherit the environment of the current process.
+    ///
+    /// Only the environment variables set on the command are passed to it.
+    pub fn with_env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+
+    /// Do not inherit the given environment variable from the current process.
+    ///
+    /// If the environment variable was set on the command, it is unset.
+    pub fn with_env_remove<S: Into<String>>(mut self, key: S) -> Self {
+        let key = key.into();
+        if let Some(env) = &mut self.env {
+            env.remove(&key);
+        }
+        self.env_remove.insert(key);
+        self
+    }
+
     /// Set all environment variables.
     pub fn with_envs(mut self, env: HashMap<String, String>) -> Self {
         self.env = Some(env);
@@ -83,39 +238,557 @@ impl Command {
         self
     }
 
+    /// Set whether the command should be killed when its
+    /// [`ChildProcess`](trait.ChildProcess.html) is dropped.
+    ///
+    /// It only applies to spawned commands.
+    pub fn with_kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    /// Set resource limits.
+    #[cfg(unix)]
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Run the command in a new session, as leader of a new process group.
+    ///
+    /// It overrides the process group.
+    #[cfg(unix)]
+    pub fn with_new_session(mut self) -> Self {
+        self.new_session = true;
+        self.process_group = None;
+        self
+    }
+
+    /// Set niceness.
+    #[cfg(unix)]
+    pub fn with_nice(mut self, nice: i32) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+
+    /// Set the process group to run the command in.
+    ///
+    /// `0` means that a new process group is created with the command as leader. It overrides the
+    /// new session.
+    #[cfg(unix)]
+    pub fn with_process_group(mut self, pgid: i32) -> Self {
+        self.new_session = false;
+        self.process_group = Some(pgid);
+        self
+    }
+
+    /// Pass the arguments to the program verbatim.
+    ///
+    /// It has no effect on other platforms than Windows.
+    pub fn with_raw_args(mut self) -> Self {
+        self.raw_args = true;
+        self
+    }
+
+    /// Set what to do with the standard error.
+    pub fn with_stderr(mut self, stderr: CommandStdio) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Set what to do with the standard output.
+    pub fn with_stdout(mut self, stdout: CommandStdio) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
     /// Set UID.
     #[cfg(unix)]
     pub fn with_uid(mut self, uid: u32) -> Self {
         self.uid = Some(uid);
         self
     }
+
+    #[inline]
+    fn to_std(&self) -> Result<std::process::Command> {
+        let mut builder = std::process::Command::new(&self.program);
+        #[cfg(windows)]
+        if self.raw_args {
+            for arg in &self.args {
+                builder.raw_arg(arg);
+            }
+        } else {
+            builder.args(&self.args);
+        }
+        #[cfg(not(windows))]
+        builder.args(&self.args);
+        builder
+            .stderr(self.stderr.to_std()?)
+            .stdout(self.stdout.to_std()?);
+        if let Some(cwd) = &self.cwd {
+            builder.current_dir(cwd);
+        }
+        if self.env_clear {
+            builder.env_clear();
+        }
+        for key in &self.env_remove {
+            builder.env_remove(key);
+        }
+        if let Some(env) = &self.env {
+            builder.envs(env);
+        }
+        #[cfg(unix)]
+        {
+            if let Some(gid) = self.gid {
+                builder.gid(gid);
+            }
+            if let Some(uid) = self.uid {
+                builder.uid(uid);
+            }
+            if let Some(pgid) = self.process_group {
+                builder.process_group(pgid);
+            }
+            let limits = self.limits;
+            let new_session = self.new_session;
+            let nice = self.nice;
+            if new_session || nice.is_some() || limits != ResourceLimits::default() {
+                // SAFETY: setsid, setpriority and setrlimit are async-signal-safe.
+                unsafe {
+                    builder.pre_exec(move || {
+                        if new_session && libc::setsid() == -1 {
+                            return Err(io::Error::last_os_error());
+                        }
+                        if let Some(nice) = nice {
+                            if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                                return Err(io::Error::last_os_error());
+                            }
+                        }
+                        let limits = [
+                            (libc::RLIMIT_AS, limits.memory),
+                            (libc::RLIMIT_CPU, limits.cpu),
+                            (libc::RLIMIT_NOFILE, limits.open_files),
+                        ];
+                        for (resource, limit) in limits {
+                            if let Some(limit) = limit {
+                                // rlim_t is not u64 on all platforms.
+                                #[allow(clippy::unnecessary_cast)]
+                                let limit = limit as libc::rlim_t;
+                                let rlimit = libc::rlimit {
+                                    rlim_cur: limit,
+                                    rlim_max: limit,
+                                };
+                                if libc::setrlimit(resource, &rlimit) == -1 {
+                                    return Err(io::Error::last_os_error());
+                                }
+                            }
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+        Ok(builder)
+    }
+}
+
+impl Debug for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let env: Option<BTreeMap<&String, &str>> = self
+            .env
+            .as_ref()
+            .map(|env| env.keys().map(|key| (key, "***")).collect());
+        let mut dbg = f.debug_struct("Command");
+        dbg.field("args", &self.args)
+            .field("cwd", &self.cwd)
+            .field("env", &env)
+            .field("env_clear", &self.env_clear)
+            .field("env_remove", &self.env_remove);
+        #[cfg(unix)]
+        dbg.field("gid", &self.gid);
+        dbg.field("kill_on_drop", &self.kill_on_drop);
+        #[cfg(unix)]
+        dbg.field("limits", &self.limits)
+            .field("new_session", &self.new_session)
+            .field("nice", &self.nice)
+            .field("process_group", &self.process_group);
+        dbg.field("program", &self.program)
+            .field("raw_args", &self.raw_args)
+            .field("stderr", &self.stderr)
+            .field("stdout", &self.stdout);
+        #[cfg(unix)]
+        dbg.field("uid", &self.uid);
+        dbg.finish()
+    }
+}
+
+impl Display for Command {
+    /// Formats the command as a shell-quoted string.
+    ///
+    /// Environment variables are prepended with their values redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(env) = &self.env {
+            let mut keys: Vec<&String> = env.keys().collect();
+            keys.sort();
+            for key in keys {
+                write!(f, "{key}=*** ")?;
+            }
+        }
+        write!(f, "{}", shell_words::quote(&self.program.to_string_lossy()))?;
+        for arg in &self.args {
+            write!(f, " {}", shell_words::quote(&arg.to_string_lossy()))?;
+        }
+        Ok(())
+    }
+}
+
+// CommandParseError
+
+/// Error returned when a command can't be parsed.
+///
+/// **This is supported on `feature=cmd` only.**
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CommandParseError {
+    /// The string does not contain any program.
+    Empty,
+    /// The string is not a valid shell-quoted string.
+    Invalid(shell_words::ParseError),
+}
+
+impl Display for CommandParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "command is empty"),
+            Self::Invalid(err) => write!(f, "invalid command: {err}"),
+        }
+    }
+}
+
+impl Error for CommandParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Empty => None,
+            Self::Invalid(err) => Some(err),
+        }
+    }
+}
+
+// CommandStdio
+
+/// What to do with a standard stream of a command.
+///
+/// **This is supported on `feature=cmd` only.**
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum CommandStdio {
+    /// The stream is written to the given file, which is created or truncated.
+    File(PathBuf),
+    /// The stream is inherited from the current process.
+    Inherit,
+    /// The stream is discarded.
+    Null,
+    /// The stream is captured.
+    Piped,
+}
+
+impl CommandStdio {
+    #[inline]
+    fn to_std(&self) -> Result<Stdio> {
+        match self {
+            Self::File(path) => File::create(path).map(Stdio::from),
+            Self::Inherit => Ok(Stdio::inherit()),
+            Self::Null => Ok(Stdio::null()),
+            Self::Piped => Ok(Stdio::piped()),
+        }
+    }
+}
+
+// ResourceLimits
+
+/// Resource limits of a command.
+///
+/// Each limit is applied as both the soft and the hard limit.
+///
+/// **This is supported on `feature=cmd` and unix only.**
+#[cfg(unix)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ResourceLimits {
+    /// The maximum CPU time in seconds.
+    pub cpu: Option<u64>,
+    /// The maximum size of the virtual memory in bytes.
+    pub memory: Option<u64>,
+    /// The maximum number of open file descriptors.
+    pub open_files: Option<u64>,
+}
+
+// ChildProcess
+
+/// A spawned child process.
+///
+/// **This is supported on `feature=cmd` only.**
+#[async_trait]
+pub trait ChildProcess: Send + Sync {
+    /// Returns the OS-assigned process identifier of the child.
+    ///
+    /// `None` is returned if the child has already been polled to completion.
+    fn id(&self) -> Option<u32>;
+
+    /// Forces the child to exit and waits for it.
+    async fn kill(&mut self) -> Result<()>;
+
+    /// Takes the standard error of the child.
+    ///
+    /// `None` is returned if it has already been taken or if it is not piped.
+    fn take_stderr(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>>;
+
+    /// Takes the standard input of the child.
+    ///
+    /// `None` is returned if it has already been taken.
+    fn take_stdin(&mut self) -> Option<Box<dyn AsyncWrite + Send + Unpin>>;
+
+    /// Takes the standard output of the child.
+    ///
+    /// `None` is returned if it has already been taken or if it is not piped.
+    fn take_stdout(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>>;
+
+    /// Returns the exit status of the child if it has exited, without blocking.
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>>;
+
+    /// Waits for the child to exit and returns its exit status.
+    async fn wait(&mut self) -> Result<ExitStatus>;
+}
+
+// DefaultChildProcess
+
+/// Default implementation of [`ChildProcess`](trait.ChildProcess.html).
+///
+/// **This is supported on `feature=cmd` only.**
+pub struct DefaultChildProcess(Child);
+
+#[async_trait]
+impl ChildProcess for DefaultChildProcess {
+    fn id(&self) -> Option<u32> {
+        self.0.id()
+    }
+
+    async fn kill(&mut self) -> Result<()> {
+        self.0.kill().await
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>> {
+        self.0
+            .stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as Box<dyn AsyncRead + Send + Unpin>)
+    }
+
+    fn take_stdin(&mut self) -> Option<Box<dyn AsyncWrite + Send + Unpin>> {
+        self.0
+            .stdin
+            .take()
+            .map(|stdin| Box::new(stdin) as Box<dyn AsyncWrite + Send + Unpin>)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>> {
+        self.0
+            .stdout
+            .take()
+            .map(|stdout| Box::new(stdout) as Box<dyn AsyncRead + Send + Unpin>)
+    }
+
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        self.0
+            .try_wait()
+            .map(|status| status.map(|status| status.into()))
+    }
+
+    async fn wait(&mut self) -> Result<ExitStatus> {
+        self.0.wait().await.map(|status| status.into())
+    }
+}
+
+// CommandFailedError
+
+/// Error returned when a command exits with a non-zero status.
+///
+/// It is returned inside an [`io::Error`](https://doc.rust-lang.org/std/io/struct.Error.html) by
+/// `run_checked`, see [`downcast`](#method.downcast).
+///
+/// **This is supported on `feature=cmd` only.**
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommandFailedError {
+    /// The command that failed.
+    pub cmd: Command,
+    /// The exit code of the command.
+    ///
+    /// `None` if the command was terminated by a signal.
+    pub code: Option<i32>,
+    /// The standard error output of the command.
+    pub stderr: Vec<u8>,
+}
+
+impl CommandFailedError {
+    /// Returns the error wrapped in `err`, if any.
+    ///
+    /// It is `None` if the command couldn't be run at all.
+    pub fn downcast(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl Display for CommandFailedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}", self.cmd)?;
+        match self.code {
+            Some(code) => write!(f, "` failed with exit code {code}")?,
+            None => write!(f, "` was terminated by a signal")?,
+        }
+        let stderr = String::from_utf8_lossy(&self.stderr);
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            write!(f, ": {stderr}")?;
+        }
+        Ok(())
+    }
 }
 
+impl Error for CommandFailedError {}
+
 // CommandOutput
 
 /// The output of a command.
 ///
 /// **This is supported on `feature=cmd` only.**
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct CommandOutput {
     /// The exit code of the command.
+    ///
+    /// `None` if the command was terminated by a signal.
     pub code: Option<i32>,
+    /// The signal that terminated the command.
+    ///
+    /// It is always `None` on non-unix platforms.
+    pub signal: Option<i32>,
     /// The standard error output of the command.
+    ///
+    /// It is empty if the standard error is not [`CommandStdio::Piped`](enum.CommandStdio.html#variant.Piped).
     pub stderr: Vec<u8>,
     /// The standard output of the command.
+    ///
+    /// It is empty if the standard output is not [`CommandStdio::Piped`](enum.CommandStdio.html#variant.Piped).
     pub stdout: Vec<u8>,
 }
 
+impl CommandOutput {
+    /// Returns the standard error output as UTF-8.
+    ///
+    /// If it is not valid UTF-8, an error is returned.
+    pub fn stderr_utf8(&self) -> std::result::Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.stderr)
+    }
+
+    /// Returns the standard error output as UTF-8.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    pub fn stderr_utf8_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+
+    /// Returns the standard output as UTF-8.
+    ///
+    /// If it is not valid UTF-8, an error is returned.
+    pub fn stdout_utf8(&self) -> std::result::Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.stdout)
+    }
+
+    /// Returns the standard output as UTF-8.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    pub fn stdout_utf8_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    /// Returns `true` if the command exited with code 0.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    #[inline]
+    fn check(self, cmd: &Command) -> Result<Self> {
+        if self.success() {
+            Ok(self)
+        } else {
+            Err(io::Error::other(CommandFailedError {
+                cmd: cmd.clone(),
+                code: self.code,
+                stderr: self.stderr,
+            }))
+        }
+    }
+}
+
+impl Display for CommandOutput {
+    /// Formats the status and the size of the outputs.
+    ///
+    /// The content of the outputs is never formatted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exited with code {code}")?,
+            (None, Some(signal)) => write!(f, "terminated by signal {signal}")?,
+            (None, None) => write!(f, "terminated")?,
+        }
+        write!(
+            f,
+            " (stdout: {} bytes, stderr: {} bytes)",
+            self.stdout.len(),
+            self.stderr.len()
+        )
+    }
+}
+
 impl From<Output> for CommandOutput {
     fn from(output: Output) -> Self {
+        let status = ExitStatus::from(output.status);
         Self {
-            code: output.status.code(),
+            code: status.code,
+            signal: status.signal,
             stderr: output.stderr,
             stdout: output.stdout,
         }
     }
 }
 
+// ExitStatus
+
+/// The exit status of a process.
+///
+/// **This is supported on `feature=cmd` only.**
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct ExitStatus {
+    /// The exit code of the process.
+    ///
+    /// `None` if the process was terminated by a signal.
+    pub code: Option<i32>,
+    /// The signal that terminated the process.
+    ///
+    /// It is always `None` on non-unix platforms.
+    pub signal: Option<i32>,
+}
+
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> Self {
+        Self {
+            code: status.code(),
+            #[cfg(unix)]
+            signal: status.signal(),
+            #[cfg(not(unix))]
+            signal: None,
+        }
+    }
+}
+
 // CommandRunner
 
 /// A trait for running commands.
@@ -127,54 +800,1350 @@ impl From<Output> for CommandOutput {
 pub trait CommandRunner: Send + Sync {
     /// Runs the given command.
     async fn run(&self, cmd: &Command) -> Result<CommandOutput>;
+
+    /// Runs the given command and fails if it does not exit with code 0.
+    ///
+    /// In this case, the inner error is a [`CommandFailedError`](struct.CommandFailedError.html),
+    /// which can be retrieved with
+    /// [`CommandFailedError::downcast`](struct.CommandFailedError.html#method.downcast).
+    async fn run_checked(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.run(cmd).await?.check(cmd)
+    }
+
+    /// Spawns the given command without waiting for it to complete.
+    ///
+    /// The standard input of the child is piped.
+    ///
+    /// By default, an error of kind `Unsupported` is returned.
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("spawning {:?} is not supported", cmd.program),
+        ))
+    }
 }
 
 // DefaultCommandRunner
 
 /// Default implementation of [`CommandRunner`](trait.CommandRunner.html).
 ///
+/// If `feature=cmd-tracing` is enabled, each run is wrapped in a `run_command` span that records
+/// the program, the arguments (truncated), the exit code and the duration.
+///
 /// **This is supported on `feature=cmd` only.**
 ///
 /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/cmd.rs).
 pub struct DefaultCommandRunner;
 
-#[async_trait]
-impl CommandRunner for DefaultCommandRunner {
-    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
-        let mut builder = tokio::process::Command::new(&cmd.program);
-        builder.args(&cmd.args);
-        if let Some(cwd) = &cmd.cwd {
-            builder.current_dir(cwd);
-        }
-        if let Some(env) = &cmd.env {
-            builder.envs(env);
-        }
-        if cfg!(unix) {
-            if let Some(gid) = cmd.gid {
-                builder.gid(gid);
+#[cfg(feature = "cmd-tracing")]
+impl DefaultCommandRunner {
+    #[inline]
+    async fn trace<F: Future<Output = Result<CommandOutput>>>(
+        cmd: &Command,
+        run: F,
+    ) -> Result<CommandOutput> {
+        let args = traced_args(cmd);
+        let span = info_span!(
+            "run_command",
+            program = ?cmd.program,
+            args = %args,
+            code = field::Empty,
+            duration_ms = field::Empty,
+        );
+        let start = Instant::now();
+        let res = run.instrument(span.clone()).await;
+        span.record("duration_ms", start.elapsed().as_millis() as u64);
+        match &res {
+            Ok(output) => {
+                if let Some(code) = output.code {
+                    span.record("code", code);
+                }
+                debug!(parent: &span, "command completed");
             }
-            if let Some(uid) = cmd.uid {
-                builder.uid(uid);
+            Err(err) => {
+                debug!(parent: &span, details = %err, "command failed to run");
             }
         }
-        let output = builder.output().await?;
-        Ok(output.into())
+        res
     }
 }
 
-// MockCommandRunner
+#[async_trait]
+impl CommandRunner for DefaultCommandRunner {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        let run = async {
+            // `output` would pipe the standard streams regardless of the command.
+            let output = tokio::process::Command::from(cmd.to_std()?)
+                .stdin(Stdio::null())
+                .spawn()?
+                .wait_with_output()
+                .await?;
+            Ok(output.into())
+        };
+        #[cfg(feature = "cmd-tracing")]
+        let run = Self::trace(cmd, run);
+        run.await
+    }
 
-#[cfg(feature = "mock")]
-mockall::mock! {
-    /// `mockall` implementation of [`CommandRunner`](trait.CommandRunner.html).
-    ///
-    /// **This is supported on `feature=cmd,mock` only.**
-    ///
-    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/cmd.rs).
-    pub CommandRunner {}
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        let child = tokio::process::Command::from(cmd.to_std()?)
+            .kill_on_drop(cmd.kill_on_drop)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        Ok(Box::new(DefaultChildProcess(child)))
+    }
+}
 
-    #[async_trait]
+// MeteredCommandRunner
+
+/// Implementation of [`CommandRunner`](trait.CommandRunner.html) that calls a hook with the
+/// result and the duration of each run of an inner runner.
+///
+/// It is useful to export metrics. Spawned commands are not measured.
+///
+/// **This is supported on `feature=cmd` only.**
+pub struct MeteredCommandRunner<RUNNER: CommandRunner> {
+    hook: Box<RunHook>,
+    inner: RUNNER,
+}
+
+impl<RUNNER: CommandRunner> MeteredCommandRunner<RUNNER> {
+    /// Creates a new runner that delegates to `inner` and calls `hook` after each run.
+    pub fn new<F: Fn(&Command, &Result<CommandOutput>, Duration) + Send + Sync + 'static>(
+        inner: RUNNER,
+        hook: F,
+    ) -> Self {
+        Self {
+            hook: Box::new(hook),
+            inner,
+        }
+    }
+}
+
+#[async_trait]
+impl<RUNNER: CommandRunner> CommandRunner for MeteredCommandRunner<RUNNER> {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        let start = Instant::now();
+        let res = self.inner.run(cmd).await;
+        (self.hook)(cmd, &res, start.elapsed());
+        res
+    }
+
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        self.inner.spawn(cmd).await
+    }
+}
+
+// RetryPolicy
+
+/// Policy describing when and how a command should be retried.
+///
+/// **This is supported on `feature=cmd` only.**
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// The delay before the first retry.
+    pub backoff: Duration,
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+    /// The factor by which the delay is multiplied after each retry.
+    pub multiplier: u32,
+    /// The predicate that returns `true` if the result of an attempt should be retried.
+    pub retryable: Arc<RetryPredicate>,
+}
+
+impl RetryPolicy {
+    /// Set the delay before the first retry.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the maximum number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the maximum delay between two attempts.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set the backoff multiplier.
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set the predicate that returns `true` if the result of an attempt should be retried.
+    pub fn with_retryable<F: Fn(&Result<CommandOutput>) -> bool + Send + Sync + 'static>(
+        mut self,
+        retryable: F,
+    ) -> Self {
+        self.retryable = Arc::new(retryable);
+        self
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Creates a policy that makes at most 3 attempts, waiting 100ms then 200ms, and retries
+    /// every error and non-zero exit code.
+    fn default() -> Self {
+        Self {
+            backoff: Duration::from_millis(100),
+            max_attempts: 3,
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            retryable: Arc::new(|res| !matches!(res, Ok(output) if output.success())),
+        }
+    }
+}
+
+// RetryingCommandRunner
+
+/// Implementation of [`CommandRunner`](trait.CommandRunner.html) that retries commands with an
+/// exponential backoff.
+///
+/// Only [`run`](trait.CommandRunner.html#tymethod.run) is retried, commands are spawned once.
+///
+/// **This is supported on `feature=cmd` only.**
+pub struct RetryingCommandRunner<RUNNER: CommandRunner> {
+    inner: RUNNER,
+    policy: RetryPolicy,
+}
+
+impl<RUNNER: CommandRunner> RetryingCommandRunner<RUNNER> {
+    /// Creates a new runner that delegates to `inner` according to the given policy.
+    pub fn new(inner: RUNNER, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl<RUNNER: CommandRunner> CommandRunner for RetryingCommandRunner<RUNNER> {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        let mut attempt = 1;
+        let mut delay = self.policy.backoff;
+        loop {
+            let res = self.inner.run(cmd).await;
+            if attempt >= self.policy.max_attempts || !(self.policy.retryable)(&res) {
+                return res;
+            }
+            warn!(attempt, program = ?cmd.program, ?delay, "command failed, retrying");
+            sleep(delay).await;
+            delay = delay
+                .saturating_mul(self.policy.multiplier)
+                .min(self.policy.max_backoff);
+            attempt += 1;
+        }
+    }
+
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        self.inner.spawn(cmd).await
+    }
+}
+
+// ThrottledCommandRunner
+
+/// Implementation of [`CommandRunner`](trait.CommandRunner.html) that limits the number of
+/// commands run concurrently by an inner runner.
+///
+/// Only [`run`](trait.CommandRunner.html#tymethod.run) is throttled, spawned commands are not
+/// counted.
+///
+/// **This is supported on `feature=cmd` only.**
+pub struct ThrottledCommandRunner<RUNNER: CommandRunner> {
+    inner: RUNNER,
+    max_concurrent: usize,
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    sem: Semaphore,
+}
+
+impl<RUNNER: CommandRunner> ThrottledCommandRunner<RUNNER> {
+    /// Creates a new runner that delegates at most `max_concurrent` commands at a time to `inner`.
+    ///
+    /// # Panics
+    /// Panics if `max_concurrent` is `0`.
+    pub fn new(inner: RUNNER, max_concurrent: usize) -> Self {
+        assert!(max_concurrent > 0, "max_concurrent must not be 0");
+        Self {
+            inner,
+            max_concurrent,
+            queued: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            sem: Semaphore::new(max_concurrent),
+        }
+    }
+
+    /// Returns the maximum number of commands run concurrently.
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Returns the number of commands waiting to be run.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of commands currently running.
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl<RUNNER: CommandRunner> CommandRunner for ThrottledCommandRunner<RUNNER> {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        let queued = Counter::increment(&self.queued);
+        let permit = self.sem.acquire().await.map_err(io::Error::other)?;
+        drop(queued);
+        let _running = Counter::increment(&self.running);
+        let output = self.inner.run(cmd).await;
+        drop(permit);
+        output
+    }
+
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        self.inner.spawn(cmd).await
+    }
+}
+
+// Counter
+
+struct Counter<'a>(&'a AtomicUsize);
+
+impl<'a> Counter<'a> {
+    #[inline]
+    fn increment(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for Counter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// SyncCommandRunner
+
+/// A trait for running commands synchronously.
+///
+/// **This is supported on `feature=cmd` only.**
+pub trait SyncCommandRunner: Send + Sync {
+    /// Runs the given command and blocks until it completes.
+    fn run(&self, cmd: &Command) -> Result<CommandOutput>;
+
+    /// Runs the given command and fails if it does not exit with code 0.
+    ///
+    /// In this case, the inner error is a [`CommandFailedError`](struct.CommandFailedError.html),
+    /// which can be retrieved with
+    /// [`CommandFailedError::downcast`](struct.CommandFailedError.html#method.downcast).
+    fn run_checked(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.run(cmd)?.check(cmd)
+    }
+}
+
+// DefaultSyncCommandRunner
+
+/// Default implementation of [`SyncCommandRunner`](trait.SyncCommandRunner.html).
+///
+/// **This is supported on `feature=cmd` only.**
+pub struct DefaultSyncCommandRunner;
+
+impl SyncCommandRunner for DefaultSyncCommandRunner {
+    fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        let output = cmd.to_std()?.output()?;
+        Ok(output.into())
+    }
+}
+
+// CommandMatcher
+
+/// A matcher of commands used by [`FakeCommandRunner`](struct.FakeCommandRunner.html).
+///
+/// **This is supported on `feature=cmd,mock` only.**
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct CommandMatcher {
+    args: Option<Arc<ArgsPredicate>>,
+    program: OsString,
+}
+
+#[cfg(feature = "mock")]
+impl CommandMatcher {
+    /// Creates a new matcher that matches all commands running the given program.
+    pub fn new<S: Into<OsString>>(program: S) -> Self {
+        Self {
+            args: None,
+            program: program.into(),
+        }
+    }
+
+    /// Only match commands with exactly the given arguments.
+    pub fn with_args<S: Into<OsString>, I: IntoIterator<Item = S>>(self, args: I) -> Self {
+        let args: Vec<OsString> = args.into_iter().map(|arg| arg.into()).collect();
+        self.with_args_matching(move |actual| actual == args.as_slice())
+    }
+
+    /// Only match commands whose arguments satisfy the given predicate.
+    pub fn with_args_matching<F: Fn(&[OsString]) -> bool + Send + Sync + 'static>(
+        mut self,
+        f: F,
+    ) -> Self {
+        self.args = Some(Arc::new(f));
+        self
+    }
+
+    /// Returns `true` if the given command matches.
+    pub fn matches(&self, cmd: &Command) -> bool {
+        if cmd.program != self.program {
+            return false;
+        }
+        match &self.args {
+            Some(args) => args(&cmd.args),
+            None => true,
+        }
+    }
+}
+
+// FakeCommandRunner
+
+/// Implementation of [`CommandRunner`](trait.CommandRunner.html) and
+/// [`SyncCommandRunner`](trait.SyncCommandRunner.html) that returns canned outputs.
+///
+/// The output of a command is the one of the first matcher that matches it, regardless of the
+/// order in which commands are run. By default, an error is returned if no matcher matches.
+///
+/// Spawning a command is not supported and always returns an error.
+///
+/// **This is supported on `feature=cmd,mock` only.**
+#[cfg(feature = "mock")]
+#[derive(Clone, Default)]
+pub struct FakeCommandRunner {
+    outputs: Vec<(CommandMatcher, CommandOutput)>,
+    panic_on_unexpected: bool,
+}
+
+#[cfg(feature = "mock")]
+impl FakeCommandRunner {
+    /// Creates a new runner that does not expect any command.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the output to return when a command matches.
+    pub fn with_output(mut self, matcher: CommandMatcher, output: CommandOutput) -> Self {
+        self.outputs.push((matcher, output));
+        self
+    }
+
+    /// Panic instead of returning an error if a command is unexpected.
+    pub fn with_panic_on_unexpected(mut self) -> Self {
+        self.panic_on_unexpected = true;
+        self
+    }
+
+    #[inline]
+    fn output(&self, cmd: &Command) -> Result<CommandOutput> {
+        let output = self
+            .outputs
+            .iter()
+            .find(|(matcher, _)| matcher.matches(cmd))
+            .map(|(_, output)| output.clone());
+        match output {
+            Some(output) => Ok(output),
+            None if self.panic_on_unexpected => panic!("unexpected command: {cmd}"),
+            None => Err(io::Error::other(format!("unexpected command: {cmd}"))),
+        }
+    }
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl CommandRunner for FakeCommandRunner {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.output(cmd)
+    }
+
+    async fn spawn(&self, _cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "spawning a command is not supported by FakeCommandRunner",
+        ))
+    }
+}
+
+#[cfg(feature = "mock")]
+impl SyncCommandRunner for FakeCommandRunner {
+    fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.output(cmd)
+    }
+}
+
+// RecordingCommandRunner
+
+/// Implementation of [`CommandRunner`](trait.CommandRunner.html) and
+/// [`SyncCommandRunner`](trait.SyncCommandRunner.html) that records every command before
+/// delegating it to an inner runner.
+///
+/// Clones share the same record.
+///
+/// **This is supported on `feature=cmd,mock` only.**
+#[cfg(feature = "mock")]
+pub struct RecordingCommandRunner<RUNNER> {
+    cmds: Arc<Mutex<Vec<Command>>>,
+    inner: Arc<RUNNER>,
+}
+
+#[cfg(feature = "mock")]
+impl<RUNNER> RecordingCommandRunner<RUNNER> {
+    /// Creates a new runner that delegates to `inner`.
+    pub fn new(inner: RUNNER) -> Self {
+        Self {
+            cmds: Arc::new(Mutex::new(vec![])),
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Clears the recorded commands.
+    pub fn clear(&self) {
+        self.cmds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Returns the recorded commands, in the order in which they were run or spawned.
+    pub fn commands(&self) -> Vec<Command> {
+        self.cmds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    #[inline]
+    fn record(&self, cmd: &Command) {
+        self.cmds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(cmd.clone());
+    }
+}
+
+#[cfg(feature = "mock")]
+impl<RUNNER> Clone for RecordingCommandRunner<RUNNER> {
+    fn clone(&self) -> Self {
+        Self {
+            cmds: self.cmds.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl<RUNNER: CommandRunner> CommandRunner for RecordingCommandRunner<RUNNER> {
+    async fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.record(cmd);
+        self.inner.run(cmd).await
+    }
+
+    async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>> {
+        self.record(cmd);
+        self.inner.spawn(cmd).await
+    }
+}
+
+#[cfg(feature = "mock")]
+impl<RUNNER: SyncCommandRunner> SyncCommandRunner for RecordingCommandRunner<RUNNER> {
+    fn run(&self, cmd: &Command) -> Result<CommandOutput> {
+        self.record(cmd);
+        self.inner.run(cmd)
+    }
+}
+
+// MockChildProcess
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`ChildProcess`](trait.ChildProcess.html).
+    ///
+    /// **This is supported on `feature=cmd,mock` only.**
+    pub ChildProcess {}
+
+    #[async_trait]
+    impl ChildProcess for ChildProcess {
+        fn id(&self) -> Option<u32>;
+        async fn kill(&mut self) -> Result<()>;
+        fn take_stderr(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>>;
+        fn take_stdin(&mut self) -> Option<Box<dyn AsyncWrite + Send + Unpin>>;
+        fn take_stdout(&mut self) -> Option<Box<dyn AsyncRead + Send + Unpin>>;
+        fn try_wait(&mut self) -> Result<Option<ExitStatus>>;
+        async fn wait(&mut self) -> Result<ExitStatus>;
+    }
+}
+
+// MockCommandRunner
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`CommandRunner`](trait.CommandRunner.html).
+    ///
+    /// **This is supported on `feature=cmd,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/cmd.rs).
+    pub CommandRunner {}
+
+    #[async_trait]
     impl CommandRunner for CommandRunner {
         async fn run(&self, cmd: &Command) -> Result<CommandOutput>;
+        async fn spawn(&self, cmd: &Command) -> Result<Box<dyn ChildProcess>>;
+    }
+}
+
+// MockSyncCommandRunner
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`SyncCommandRunner`](trait.SyncCommandRunner.html).
+    ///
+    /// **This is supported on `feature=cmd,mock` only.**
+    pub SyncCommandRunner {}
+
+    impl SyncCommandRunner for SyncCommandRunner {
+        fn run(&self, cmd: &Command) -> Result<CommandOutput>;
+    }
+}
+
+// Functions
+
+#[cfg(feature = "cmd-tracing")]
+#[inline]
+fn traced_args(cmd: &Command) -> String {
+    let mut args = cmd
+        .args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if args.len() > TRACED_ARGS_MAX_LEN {
+        let len = (0..=TRACED_ARGS_MAX_LEN)
+            .rev()
+            .find(|idx| args.is_char_boundary(*idx))
+            .unwrap_or(0);
+        args.truncate(len);
+        args.push_str("...");
+    }
+    args
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_os_string<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<OsString, D::Error> {
+    <String as serde::Deserialize>::deserialize(deserializer).map(OsString::from)
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_os_strings<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<OsString>, D::Error> {
+    <Vec<String> as serde::Deserialize>::deserialize(deserializer)
+        .map(|strings| strings.into_iter().map(OsString::from).collect())
+}
+
+#[cfg(feature = "serde")]
+fn serialize_os_string<S: serde::Serializer>(
+    string: &OsString,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&string.to_string_lossy())
+}
+
+#[cfg(feature = "serde")]
+fn serialize_os_strings<S: serde::Serializer>(
+    strings: &[OsString],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(strings.iter().map(|string| string.to_string_lossy()))
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod command_runner {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn spawn_unsupported() {
+            struct Runner;
+
+            #[async_trait]
+            impl CommandRunner for Runner {
+                async fn run(&self, _cmd: &Command) -> Result<CommandOutput> {
+                    Err(io::ErrorKind::Other.into())
+                }
+            }
+
+            let err = Runner
+                .spawn(&Command::new("git"))
+                .await
+                .err()
+                .expect("spawn should fail");
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[cfg(unix)]
+    mod default_command_runner {
+        use tokio::{io::AsyncReadExt, time::timeout};
+
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn env_clear() {
+            let cmd = Command::shell("echo \"${HOME-unset}:$FOO\"")
+                .with_env_clear()
+                .with_env("FOO", "bar");
+            let output = DefaultCommandRunner
+                .run(&cmd)
+                .await
+                .expect("failed to run command");
+            assert_eq!(output.stdout, b"unset:bar\n");
+        }
+
+        #[tokio::test]
+        async fn env_remove() {
+            let cmd = Command::shell("echo \"${HOME-unset}:${FOO-unset}\"")
+                .with_env("FOO", "bar")
+                .with_env_remove("FOO")
+                .with_env_remove("HOME");
+            let output = DefaultCommandRunner
+                .run(&cmd)
+                .await
+                .expect("failed to run command");
+            assert_eq!(output.stdout, b"unset:unset\n");
+        }
+
+        #[tokio::test]
+        async fn kill_on_drop() {
+            let cmd = Command::new("sleep").with_arg("30").with_kill_on_drop(true);
+            let mut child = DefaultCommandRunner
+                .spawn(&cmd)
+                .await
+                .expect("failed to spawn command");
+            let mut stdout = child.take_stdout().expect("stdout should be piped");
+            drop(child);
+            let mut buf = vec![];
+            timeout(Duration::from_secs(5), stdout.read_to_end(&mut buf))
+                .await
+                .expect("command should be killed")
+                .expect("failed to read stdout");
+        }
+
+        #[tokio::test]
+        async fn limits() {
+            let limits = ResourceLimits {
+                open_files: Some(64),
+                ..Default::default()
+            };
+            let cmd = Command::shell("ulimit -n").with_limits(limits);
+            let output = DefaultCommandRunner
+                .run(&cmd)
+                .await
+                .expect("failed to run command");
+            assert_eq!(output.stdout, b"64\n");
+        }
+
+        #[tokio::test]
+        async fn new_session() {
+            let cmd = Command::new("sleep")
+                .with_arg("30")
+                .with_kill_on_drop(true)
+                .with_new_session();
+            let child = DefaultCommandRunner
+                .spawn(&cmd)
+                .await
+                .expect("failed to spawn command");
+            let pid = child.id().expect("child should be running") as libc::pid_t;
+            // SAFETY: getpgid and getsid have no precondition.
+            let (pgid, sid) = unsafe { (libc::getpgid(pid), libc::getsid(pid)) };
+            assert_eq!(pgid, pid);
+            assert_eq!(sid, pid);
+        }
+
+        #[tokio::test]
+        async fn nice() {
+            // SAFETY: getpriority has no precondition.
+            let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+            let nice = (current + 1).min(19);
+            let cmd = Command::new("sleep")
+                .with_arg("30")
+                .with_kill_on_drop(true)
+                .with_nice(nice);
+            let child = DefaultCommandRunner
+                .spawn(&cmd)
+                .await
+                .expect("failed to spawn command");
+            let pid = child.id().expect("child should be running");
+            // SAFETY: getpriority has no precondition.
+            let prio = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+            assert_eq!(prio, nice);
+        }
+
+        #[tokio::test]
+        async fn output_utf8() {
+            let output = DefaultCommandRunner
+                .run(&Command::shell("printf 'caf\\303\\251\\377'"))
+                .await
+                .expect("failed to run command");
+            assert!(output.stdout_utf8().is_err());
+            assert_eq!(output.stdout_utf8_lossy(), "café\u{fffd}");
+            assert_eq!(output.stderr_utf8().expect("invalid UTF-8"), "");
+        }
+
+        #[tokio::test]
+        async fn process_group() {
+            let cmd = Command::new("sleep")
+                .with_arg("30")
+                .with_kill_on_drop(true)
+                .with_process_group(0);
+            let child = DefaultCommandRunner
+                .spawn(&cmd)
+                .await
+                .expect("failed to spawn command");
+            let pid = child.id().expect("child should be running") as libc::pid_t;
+            // SAFETY: getpgid and getsid have no precondition.
+            let (pgid, sid) = unsafe { (libc::getpgid(pid), libc::getsid(pid)) };
+            assert_eq!(pgid, pid);
+            // SAFETY: getsid has no precondition.
+            assert_eq!(sid, unsafe { libc::getsid(0) });
+        }
+
+        #[tokio::test]
+        async fn run_checked_failed() {
+            let cmd = Command::shell("echo oops >&2; exit 3");
+            let err = DefaultCommandRunner
+                .run_checked(&cmd)
+                .await
+                .expect_err("command should fail");
+            let err = CommandFailedError::downcast(&err).expect("error should be typed");
+            assert_eq!(err.cmd, cmd);
+            assert_eq!(err.code, Some(3));
+            assert_eq!(err.stderr, b"oops\n");
+        }
+
+        #[tokio::test]
+        async fn run_checked_not_found() {
+            let cmd = Command::new("mockable-not-found");
+            let err = DefaultCommandRunner
+                .run_checked(&cmd)
+                .await
+                .expect_err("command should fail");
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(CommandFailedError::downcast(&err).is_none());
+        }
+
+        #[tokio::test]
+        async fn run_checked_signal() {
+            let cmd = Command::shell("kill -TERM $$");
+            let err = DefaultCommandRunner
+                .run_checked(&cmd)
+                .await
+                .expect_err("command should fail");
+            let err = CommandFailedError::downcast(&err).expect("error should be typed");
+            assert_eq!(err.code, None);
+        }
+
+        #[tokio::test]
+        async fn shell() {
+            let output = DefaultCommandRunner
+                .run(&Command::shell("echo foo && echo bar >&2"))
+                .await
+                .expect("failed to run command");
+            assert!(output.success());
+            assert_eq!(output.stdout, b"foo\n");
+            assert_eq!(output.stderr, b"bar\n");
+        }
+
+        #[tokio::test]
+        async fn signal() {
+            let output = DefaultCommandRunner
+                .run(&Command::shell("kill -TERM $$"))
+                .await
+                .expect("failed to run command");
+            assert!(!output.success());
+            assert_eq!(output.code, None);
+            assert_eq!(output.signal, Some(libc::SIGTERM));
+        }
+
+        #[tokio::test]
+        async fn stdio() {
+            let path = std::env::temp_dir().join(format!("mockable-cmd-{}", std::process::id()));
+            let cmd = Command::shell("echo foo && echo bar >&2")
+                .with_stderr(CommandStdio::Null)
+                .with_stdout(CommandStdio::File(path.clone()));
+            let output = DefaultCommandRunner
+                .run(&cmd)
+                .await
+                .expect("failed to run command");
+            let stdout = std::fs::read(&path).expect("failed to read file");
+            std::fs::remove_file(&path).expect("failed to remove file");
+            assert!(output.stderr.is_empty());
+            assert!(output.stdout.is_empty());
+            assert_eq!(stdout, b"foo\n");
+        }
+    }
+
+    #[cfg(unix)]
+    mod default_sync_command_runner {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn run() {
+            let cmd = Command::new("echo").with_arg("foo");
+            let output = DefaultSyncCommandRunner
+                .run(&cmd)
+                .expect("failed to run command");
+            assert_eq!(output.code, Some(0));
+            assert_eq!(output.stdout, b"foo\n");
+        }
+
+        #[test]
+        fn run_checked_failed() {
+            let cmd = Command::new("false");
+            let err = DefaultSyncCommandRunner
+                .run_checked(&cmd)
+                .expect_err("command should fail");
+            let err = CommandFailedError::downcast(&err).expect("error should be typed");
+            assert_eq!(err.code, Some(1));
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    mod fake_command_runner {
+        use super::*;
+
+        // output
+
+        fn output(code: i32) -> CommandOutput {
+            CommandOutput {
+                code: Some(code),
+                signal: None,
+                stderr: vec![],
+                stdout: vec![],
+            }
+        }
+
+        // runner
+
+        fn runner() -> FakeCommandRunner {
+            FakeCommandRunner::new()
+                .with_output(CommandMatcher::new("git").with_args(["status"]), output(0))
+                .with_output(CommandMatcher::new("git"), output(1))
+        }
+
+        // Tests
+
+        #[test]
+        fn matching_args() {
+            let cmd = Command::new("git").with_arg("status");
+            let output = SyncCommandRunner::run(&runner(), &cmd).expect("failed to run command");
+            assert_eq!(output.code, Some(0));
+        }
+
+        #[test]
+        fn matching_program() {
+            let cmd = Command::new("git").with_arg("fetch");
+            let output = SyncCommandRunner::run(&runner(), &cmd).expect("failed to run command");
+            assert_eq!(output.code, Some(1));
+        }
+
+        #[test]
+        fn unexpected() {
+            let cmd = Command::new("ls");
+            SyncCommandRunner::run(&runner(), &cmd).expect_err("command should be unexpected");
+        }
+
+        #[test]
+        #[should_panic]
+        fn unexpected_panic() {
+            let cmd = Command::new("ls");
+            let runner = runner().with_panic_on_unexpected();
+            SyncCommandRunner::run(&runner, &cmd).ok();
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    mod recording_command_runner {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn commands() {
+            let cmd1 = Command::new("git").with_arg("status");
+            let cmd2 = Command::new("ls");
+            let inner = FakeCommandRunner::new().with_output(
+                CommandMatcher::new("git"),
+                CommandOutput {
+                    code: Some(0),
+                    signal: None,
+                    stderr: vec![],
+                    stdout: vec![],
+                },
+            );
+            let runner = RecordingCommandRunner::new(inner);
+            let record = runner.clone();
+            SyncCommandRunner::run(&runner, &cmd1).expect("failed to run command");
+            SyncCommandRunner::run(&runner, &cmd1).expect("failed to run command");
+            SyncCommandRunner::run(&runner, &cmd2).expect_err("command should be unexpected");
+            assert_eq!(record.commands(), vec![cmd1.clone(), cmd1, cmd2]);
+            record.clear();
+            assert!(runner.commands().is_empty());
+        }
+    }
+
+    mod throttled_command_runner {
+        use tokio::{spawn, sync::Notify, task::yield_now};
+
+        use super::*;
+
+        // BlockingCommandRunner
+
+        struct BlockingCommandRunner(Arc<Notify>);
+
+        #[async_trait]
+        impl CommandRunner for BlockingCommandRunner {
+            async fn run(&self, _cmd: &Command) -> Result<CommandOutput> {
+                self.0.notified().await;
+                Ok(CommandOutput {
+                    code: Some(0),
+                    signal: None,
+                    stderr: vec![],
+                    stdout: vec![],
+                })
+            }
+        }
+
+        // Tests
+
+        #[test]
+        #[should_panic(expected = "max_concurrent must not be 0")]
+        fn new_zero() {
+            ThrottledCommandRunner::new(BlockingCommandRunner(Arc::new(Notify::new())), 0);
+        }
+
+        #[tokio::test]
+        async fn run() {
+            let notify = Arc::new(Notify::new());
+            let runner = Arc::new(ThrottledCommandRunner::new(
+                BlockingCommandRunner(notify.clone()),
+                1,
+            ));
+            let tasks: Vec<_> = (0..2)
+                .map(|_| {
+                    let runner = runner.clone();
+                    spawn(async move { runner.run(&Command::new("ls")).await })
+                })
+                .collect();
+            while runner.running() != 1 || runner.queued() != 1 {
+                yield_now().await;
+            }
+            notify.notify_one();
+            while runner.running() != 1 || runner.queued() != 0 {
+                yield_now().await;
+            }
+            notify.notify_one();
+            for task in tasks {
+                task.await
+                    .expect("failed to join task")
+                    .expect("failed to run command");
+            }
+            assert_eq!(runner.running(), 0);
+            assert_eq!(runner.queued(), 0);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    mod retrying_command_runner {
+        use super::*;
+
+        // run
+
+        async fn run(outputs: Vec<Option<i32>>, max_attempts: usize) -> Result<CommandOutput> {
+            let cmd = Command::new("git").with_arg("fetch");
+            let mut inner = MockCommandRunner::new();
+            let mut seq = mockall::Sequence::new();
+            for code in outputs {
+                inner
+                    .expect_run()
+                    .with(mockall::predicate::eq(cmd.clone()))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .returning(move |_| {
+                        Ok(CommandOutput {
+                            code,
+                            signal: None,
+                            stderr: vec![],
+                            stdout: vec![],
+                        })
+                    });
+            }
+            let policy = RetryPolicy::default()
+                .with_backoff(Duration::ZERO)
+                .with_max_attempts(max_attempts);
+            let runner = RetryingCommandRunner::new(inner, policy);
+            runner.run(&cmd).await
+        }
+
+        // Tests
+
+        #[tokio::test]
+        async fn exhausted() {
+            let output = run(vec![Some(1), Some(1), Some(2)], 3)
+                .await
+                .expect("failed to run command");
+            assert_eq!(output.code, Some(2));
+        }
+
+        #[tokio::test]
+        async fn success_after_retry() {
+            let output = run(vec![Some(1), None, Some(0)], 3)
+                .await
+                .expect("failed to run command");
+            assert!(output.success());
+        }
+
+        #[tokio::test]
+        async fn success_first() {
+            let output = run(vec![Some(0)], 3).await.expect("failed to run command");
+            assert!(output.success());
+        }
+    }
+
+    mod metered_command_runner {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn hook() {
+            struct Runner;
+
+            #[async_trait]
+            impl CommandRunner for Runner {
+                async fn run(&self, _cmd: &Command) -> Result<CommandOutput> {
+                    sleep(Duration::from_millis(10)).await;
+                    Ok(CommandOutput {
+                        code: Some(2),
+                        signal: None,
+                        stderr: vec![],
+                        stdout: vec![],
+                    })
+                }
+            }
+
+            let calls = Arc::new(std::sync::Mutex::new(vec![]));
+            let runner = MeteredCommandRunner::new(Runner, {
+                let calls = calls.clone();
+                move |cmd: &Command, res: &Result<CommandOutput>, duration| {
+                    let code = res.as_ref().ok().and_then(|output| output.code);
+                    calls
+                        .lock()
+                        .expect("failed to lock calls")
+                        .push((cmd.clone(), code, duration));
+                }
+            });
+            let cmd = Command::new("git").with_arg("fetch");
+            let output = runner.run(&cmd).await.expect("failed to run command");
+            assert_eq!(output.code, Some(2));
+            let calls = calls.lock().expect("failed to lock calls");
+            assert_eq!(calls.len(), 1);
+            let (hooked_cmd, code, duration) = &calls[0];
+            assert_eq!(hooked_cmd, &cmd);
+            assert_eq!(*code, Some(2));
+            assert!(*duration >= Duration::from_millis(10));
+        }
+    }
+
+    #[cfg(feature = "cmd-tracing")]
+    mod traced_args {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn short() {
+            let cmd = Command::new("git").with_args(["fetch", "origin"]);
+            assert_eq!(traced_args(&cmd), "fetch origin");
+        }
+
+        #[test]
+        fn truncated() {
+            let arg = format!("{}é", "a".repeat(TRACED_ARGS_MAX_LEN - 1));
+            let cmd = Command::new("echo").with_arg(arg);
+            let args = traced_args(&cmd);
+            assert_eq!(args, format!("{}...", "a".repeat(TRACED_ARGS_MAX_LEN - 1)));
+        }
+    }
+
+    mod command_failed_error {
+        use super::*;
+
+        // Mods
+
+        mod display {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn code() {
+                let err = CommandFailedError {
+                    cmd: Command::new("git").with_arg("fetch"),
+                    code: Some(128),
+                    stderr: b"  fatal: not a git repository\n".to_vec(),
+                };
+                assert_eq!(
+                    err.to_string(),
+                    "`git fetch` failed with exit code 128: fatal: not a git repository"
+                );
+            }
+
+            #[test]
+            fn signal() {
+                let err = CommandFailedError {
+                    cmd: Command::new("sleep").with_arg("30"),
+                    code: None,
+                    stderr: vec![],
+                };
+                assert_eq!(err.to_string(), "`sleep 30` was terminated by a signal");
+            }
+        }
+    }
+
+    mod command {
+        use super::*;
+
+        // Mods
+
+        mod debug {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn redacted() {
+                let cmd = Command::new("docker").with_env("TOKEN", "secret");
+                let dbg = format!("{cmd:?}");
+                assert!(dbg.contains(r#"env: Some({"TOKEN": "***"})"#));
+                assert!(!dbg.contains("secret"));
+                let err = io::Error::other(CommandFailedError {
+                    cmd,
+                    code: Some(1),
+                    stderr: vec![],
+                });
+                assert!(!format!("{err:?}").contains("secret"));
+            }
+        }
+
+        mod display {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn redacted() {
+                let cmd = Command::new("docker")
+                    .with_arg("run")
+                    .with_arg("A=b c")
+                    .with_env("TOKEN", "secret")
+                    .with_env("HOME", "/root");
+                assert_eq!(cmd.to_string(), "HOME=*** TOKEN=*** docker run 'A=b c'");
+            }
+        }
+
+        mod parse {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn empty() {
+                let err = Command::parse("  ").unwrap_err();
+                assert_eq!(err, CommandParseError::Empty);
+            }
+
+            #[test]
+            fn invalid() {
+                let err = Command::parse("echo 'foo").unwrap_err();
+                assert!(matches!(err, CommandParseError::Invalid(_)));
+            }
+
+            #[test]
+            fn ok() {
+                let expected = Command::new("docker")
+                    .with_arg("run")
+                    .with_arg("-e")
+                    .with_arg("A=b c")
+                    .with_arg("img");
+                let cmd = Command::parse("docker run -e 'A=b c' img").expect("failed to parse");
+                assert_eq!(cmd, expected);
+            }
+        }
+
+        mod shell {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn raw_args() {
+                let cmd = Command::shell("echo \"a b\" | wc -c");
+                assert!(cmd.raw_args);
+                assert_eq!(
+                    cmd.args.last(),
+                    Some(&OsString::from("echo \"a b\" | wc -c"))
+                );
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        mod serialize {
+            use super::*;
+
+            // Tests
+
+            #[test]
+            fn round_trip() {
+                let cmd = Command::new("docker")
+                    .with_arg("run")
+                    .with_env("TOKEN", "secret");
+                let json = serde_json::to_value(&cmd).expect("failed to serialize command");
+                assert_eq!(json["program"], "docker");
+                assert_eq!(json["args"], serde_json::json!(["run"]));
+                let deserialized: Command =
+                    serde_json::from_value(json).expect("failed to deserialize command");
+                assert_eq!(deserialized, cmd);
+            }
+        }
     }
 }
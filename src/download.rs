@@ -0,0 +1,266 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{HttpClient, HttpClientRequest};
+
+// Consts
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type DownloadProgressCallback = dyn Fn(u64, Option<u64>) + Send + Sync;
+
+// DownloadOptions
+
+/// Options of a download.
+///
+/// **This is supported on `feature=download` only.**
+#[derive(Clone, Default)]
+pub struct DownloadOptions {
+    sha256: Option<String>,
+    progress: Option<Arc<DownloadProgressCallback>>,
+}
+
+impl DownloadOptions {
+    /// Creates new options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `progress` with the number of bytes written so far and the total size (if known)
+    /// while the file is written.
+    ///
+    /// With [`DefaultDownloader`](struct.DefaultDownloader.html), the content is fully received
+    /// before the file is written, so the progress doesn't track the transfer over the network.
+    pub fn with_progress<F: Fn(u64, Option<u64>) + Send + Sync + 'static>(
+        mut self,
+        progress: F,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Verifies that the SHA-256 digest of the content (hex-encoded, case insensitive) is equal
+    /// to `sha256`.
+    pub fn with_sha256<S: Into<String>>(mut self, sha256: S) -> Self {
+        self.sha256 = Some(sha256.into().to_lowercase());
+        self
+    }
+}
+
+impl Debug for DownloadOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("progress", &self.progress.is_some())
+            .field("sha256", &self.sha256)
+            .finish()
+    }
+}
+
+// Downloader
+
+/// A trait for downloading files.
+///
+/// **This is supported on `feature=download` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/download.rs).
+#[async_trait]
+pub trait Downloader: Send + Sync {
+    /// Downloads `url` to `dest` and returns the number of bytes written.
+    ///
+    /// If the response status code is not 2xx or if the checksum doesn't match, an error is
+    /// returned and `dest` is left untouched.
+    async fn download(&self, url: &str, dest: &Path, opts: DownloadOptions) -> io::Result<u64>;
+}
+
+// DefaultDownloader
+
+/// Default implementation of [`Downloader`](trait.Downloader.html) based on an
+/// [`HttpClient`](trait.HttpClient.html).
+///
+/// The whole content is received in memory, because [`HttpClient`](trait.HttpClient.html) doesn't
+/// stream the responses. Then it is written to a temporary file next to `dest` which is then
+/// renamed.
+///
+/// **This is supported on `feature=download` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/download.rs).
+pub struct DefaultDownloader<CLIENT: HttpClient> {
+    client: CLIENT,
+}
+
+impl<CLIENT: HttpClient> DefaultDownloader<CLIENT> {
+    /// Creates a new downloader.
+    pub fn new(client: CLIENT) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl<CLIENT: HttpClient> Downloader for DefaultDownloader<CLIENT> {
+    async fn download(&self, url: &str, dest: &Path, opts: DownloadOptions) -> io::Result<u64> {
+        let resp = self.client.send(HttpClientRequest::get(url)).await?;
+        if !resp.is_success() {
+            return Err(io::Error::other(format!(
+                "failed to download {url}: status code {}",
+                resp.status
+            )));
+        }
+        if let Some(expected) = &opts.sha256 {
+            let digest = hex(&Sha256::digest(&resp.body));
+            if &digest != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("checksum mismatch for {url}: expected {expected}, got {digest}"),
+                ));
+            }
+        }
+        let total = resp
+            .header("content-length")
+            .and_then(|len| len.parse().ok())
+            .or(Some(resp.body.len() as u64));
+        let tmp = tmp_path(dest);
+        let res = write(&tmp, &resp.body, total, opts.progress.as_deref()).await;
+        let res = match res {
+            Ok(()) => fs::rename(&tmp, dest).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            fs::remove_file(&tmp).await.ok();
+            return Err(err);
+        }
+        Ok(resp.body.len() as u64)
+    }
+}
+
+// MockDownloader
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Downloader`](trait.Downloader.html).
+    ///
+    /// **This is supported on `feature=download,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/download.rs).
+    pub Downloader {}
+
+    #[async_trait]
+    impl Downloader for Downloader {
+        async fn download(&self, url: &str, dest: &Path, opts: DownloadOptions) -> io::Result<u64>;
+    }
+}
+
+// Functions
+
+#[inline]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[inline]
+fn tmp_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+async fn write(
+    path: &Path,
+    data: &[u8],
+    total: Option<u64>,
+    progress: Option<&DownloadProgressCallback>,
+) -> io::Result<()> {
+    let mut file = fs::File::create(path).await?;
+    let mut written = 0;
+    if let Some(progress) = progress {
+        progress(written, total);
+    }
+    for chunk in data.chunks(CHUNK_SIZE) {
+        file.write_all(chunk).await?;
+        written += chunk.len() as u64;
+        if let Some(progress) = progress {
+            progress(written, total);
+        }
+    }
+    file.flush().await
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use std::sync::{Mutex, PoisonError};
+
+    use crate::{FakeHttpClient, HttpClientResponse};
+
+    use super::*;
+
+    // Mods
+
+    mod default_downloader {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn download() {
+            let client = FakeHttpClient::new()
+                .with_response(
+                    "GET",
+                    "https://example.com/file",
+                    HttpClientResponse::new(200).with_body("hello"),
+                )
+                .with_response(
+                    "GET",
+                    "https://example.com/missing",
+                    HttpClientResponse::new(404),
+                );
+            let downloader = DefaultDownloader::new(client);
+            let dest =
+                std::env::temp_dir().join(format!("mockable-download-{}", std::process::id()));
+            let calls = Arc::new(Mutex::new(vec![]));
+            let opts = DownloadOptions::new()
+                .with_progress({
+                    let calls = calls.clone();
+                    move |written, total| {
+                        calls
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push((written, total))
+                    }
+                })
+                .with_sha256("2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824");
+            let size = downloader
+                .download("https://example.com/file", &dest, opts)
+                .await
+                .expect("failed to download file");
+            assert_eq!(size, 5);
+            let content = fs::read_to_string(&dest)
+                .await
+                .expect("failed to read file");
+            assert_eq!(content, "hello");
+            assert_eq!(
+                *calls.lock().unwrap_or_else(PoisonError::into_inner),
+                vec![(0, Some(5)), (5, Some(5))]
+            );
+            let opts = DownloadOptions::new().with_sha256("0");
+            let err = downloader
+                .download("https://example.com/file", &dest, opts)
+                .await
+                .expect_err("checksum should mismatch");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            downloader
+                .download("https://example.com/missing", &dest, DownloadOptions::new())
+                .await
+                .expect_err("download should fail");
+            fs::remove_file(&dest).await.expect("failed to remove file");
+        }
+    }
+}
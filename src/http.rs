@@ -1,288 +1,4030 @@
-use std::{collections::HashMap, io, net::SocketAddr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
+#[cfg(feature = "http-record")]
+use axum::http::header::CONTENT_TYPE;
 use axum::{
-    body::Bytes,
-    extract::Query,
-    http::{HeaderMap, Method, StatusCode, Uri},
-    response::{Html, IntoResponse},
-    Json, Router, Server,
+    body::{Body, Bytes},
+    extract::{
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, Request,
+    },
+    http::{
+        header::{
+            ACCEPT_ENCODING, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+            ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+            ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+            ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, COOKIE,
+            ORIGIN, VARY, WWW_AUTHENTICATE,
+        },
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri,
+    },
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::GzEncoder,
+    Compression,
 };
-use serde_json::Value;
+use http_body::{Body as _, Frame, SizeHint};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    service::TowerToHyperService,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use socket2::SockRef;
+#[cfg(unix)]
+use tokio::net::UnixListener;
 use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
     spawn,
-    sync::{mpsc, oneshot},
-    task::JoinHandle,
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::{JoinHandle, JoinSet},
 };
+#[cfg(feature = "http-tls")]
+use tokio_rustls::{rustls, TlsAcceptor};
 use tracing::{error, warn};
 
+type HttpBodyPredicate = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+// Consts
+
+const MALFORMED_RESPONSE: &[u8] = b"HTTP/1.1 OOPS Malformed\r\n\r\n";
+
 // HttpRequest
 
 /// HTTP request.
 ///
+/// Fields may be added in minor versions, so it should be built with [`new`](#method.new) or
+/// with `..Default::default()`.
+///
 /// **This is supported on `feature=http` only.**
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct HttpRequest {
+    /// Body, decompressed according to `content_encoding`.
     pub body: Vec<u8>,
+    /// Original `Content-Encoding` of the body.
+    #[serde(default)]
+    pub content_encoding: Option<String>,
+    /// Headers whose value is valid UTF-8, by lowercase name.
+    ///
+    /// Repeated headers are joined with `, ` (`; ` for `Cookie`).
     pub headers: HashMap<String, String>,
     pub method: String,
     pub path: String,
+    /// Parameters of the path template of the matched route or expectation (like `id` for
+    /// `/users/:id`).
+    #[serde(default)]
+    pub path_params: HashMap<String, String>,
     pub query: HashMap<String, Vec<String>>,
+    /// All headers in the order in which they were received, including the repeated ones and the
+    /// ones whose value is not valid UTF-8.
+    #[serde(default)]
+    pub raw_headers: Vec<(String, Vec<u8>)>,
 }
 
-// HttpResponse
+impl HttpRequest {
+    /// Creates a new request with the given method (case insensitive) and path.
+    pub fn new<M: Into<String>, P: Into<String>>(method: M, path: P) -> Self {
+        Self {
+            method: method.into().to_uppercase(),
+            path: path.into(),
+            ..Default::default()
+        }
+    }
 
-/// HTTP response.
+    /// Returns the cookies sent in the `Cookie` header.
+    pub fn cookies(&self) -> HashMap<String, String> {
+        self.headers
+            .get("cookie")
+            .map(|cookies| {
+                cookies
+                    .split(';')
+                    .filter_map(|cookie| cookie.trim().split_once('='))
+                    .map(|(name, val)| (name.into(), val.into()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of the header `name` (case insensitive).
+    ///
+    /// See [`headers`](#structfield.headers).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    /// Returns all values of the header `name` (case insensitive) that are valid UTF-8.
+    ///
+    /// If [`raw_headers`](#structfield.raw_headers) is empty (like in cassettes recorded by older
+    /// versions), the value of [`headers`](#structfield.headers) is returned.
+    pub fn headers_all(&self, name: &str) -> Vec<&str> {
+        if self.raw_headers.is_empty() {
+            return self.header(name).into_iter().collect();
+        }
+        self.raw_headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .filter_map(|(_, val)| std::str::from_utf8(val).ok())
+            .collect()
+    }
+
+    /// Returns the body as JSON.
+    ///
+    /// If it is not valid JSON or doesn't match `T`, an error is returned.
+    pub fn body_json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+
+    /// Returns the body as UTF-8.
+    ///
+    /// If it is not valid UTF-8, an error is returned.
+    pub fn body_string(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.body)
+    }
+
+    /// Returns the parts of the body if the request is `multipart/form-data`.
+    ///
+    /// `None` is returned if the request is not `multipart/form-data` or if the body is malformed.
+    pub fn multipart(&self) -> Option<Vec<HttpPart>> {
+        let content_type = self.headers.get("content-type")?;
+        let mut params = content_type.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+        let boundary = params
+            .find_map(|param| param.strip_prefix("boundary="))?
+            .trim_matches('"');
+        let delimiter = format!("\r\n--{boundary}").into_bytes();
+        let body = [b"\r\n".as_slice(), &self.body].concat();
+        let mut rest = &body[find_bytes(&body, &delimiter)? + delimiter.len()..];
+        let mut parts = vec![];
+        loop {
+            if rest.starts_with(b"--") {
+                return Some(parts);
+            }
+            let part = rest.strip_prefix(b"\r\n")?;
+            let end = find_bytes(part, &delimiter)?;
+            parts.push(parse_part(&part[..end])?);
+            rest = &part[end + delimiter.len()..];
+        }
+    }
+
+    /// Returns the body decoded as `application/x-www-form-urlencoded`.
+    pub fn form(&self) -> HashMap<String, Vec<String>> {
+        form_urlencoded::parse(&self.body).into_owned().fold(
+            HashMap::new(),
+            |mut form: HashMap<String, Vec<String>>, (key, val)| {
+                form.entry(key).or_default().push(val);
+                form
+            },
+        )
+    }
+
+    /// Set body.
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set original `Content-Encoding` of the body.
+    ///
+    /// The body is not compressed, it must be the decompressed one.
+    pub fn with_content_encoding<E: Into<String>>(mut self, encoding: E) -> Self {
+        self.content_encoding = Some(encoding.into());
+        self
+    }
+
+    /// Set header.
+    ///
+    /// The name is lowercased.
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.headers.insert(name.into().to_lowercase(), val.into());
+        self
+    }
+
+    /// Set path parameter.
+    pub fn with_path_param<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.path_params.insert(name.into(), val.into());
+        self
+    }
+
+    /// Add query parameter.
+    pub fn with_query_param<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.query.entry(name.into()).or_default().push(val.into());
+        self
+    }
+
+    /// Add raw header.
+    ///
+    /// The name is lowercased. [`headers`](#structfield.headers) is not updated.
+    pub fn with_raw_header<N: Into<String>, V: Into<Vec<u8>>>(mut self, name: N, val: V) -> Self {
+        self.raw_headers
+            .push((name.into().to_lowercase(), val.into()));
+        self
+    }
+}
+
+// HttpAuth
+
+/// Authentication required by a server or a matcher.
 ///
 /// **This is supported on `feature=http` only.**
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum HttpResponse {
-    Empty,
-    Html(String),
-    Json(Value),
-    Text(String),
+pub enum HttpAuth {
+    /// `Basic` credentials.
+    Basic { password: String, username: String },
+    /// `Bearer` token equal to the given one.
+    Bearer(String),
+    /// `Bearer` JWT signed with HS256 using the given secret.
+    ///
+    /// The token must not be expired.
+    ///
+    /// **This is supported on `feature=http-jwt` only.**
+    #[cfg(feature = "http-jwt")]
+    Jwt(String),
 }
 
-// HttpServer
+impl HttpAuth {
+    /// Creates a new `Basic` authentication.
+    pub fn basic<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self::Basic {
+            password: password.into(),
+            username: username.into(),
+        }
+    }
 
-/// Simple HTTP server that listen all requests.
+    /// Returns `true` if the `Authorization` header of the request satisfies the authentication.
+    pub fn verify(&self, req: &HttpRequest) -> bool {
+        req.headers
+            .get("authorization")
+            .map(|header| self.verify_header(header))
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn challenge(&self) -> &'static str {
+        match self {
+            Self::Basic { .. } => "Basic realm=\"mockable\"",
+            _ => "Bearer",
+        }
+    }
+
+    #[inline]
+    fn verify_header(&self, header: &str) -> bool {
+        let (scheme, credentials) = match header.trim().split_once(' ') {
+            Some((scheme, credentials)) => (scheme, credentials.trim()),
+            None => return false,
+        };
+        match self {
+            Self::Basic { password, username } => {
+                scheme.eq_ignore_ascii_case("basic")
+                    && BASE64
+                        .decode(credentials)
+                        .map(|decoded| decoded == format!("{username}:{password}").as_bytes())
+                        .unwrap_or_default()
+            }
+            Self::Bearer(token) => scheme.eq_ignore_ascii_case("bearer") && credentials == token,
+            #[cfg(feature = "http-jwt")]
+            Self::Jwt(secret) => {
+                scheme.eq_ignore_ascii_case("bearer")
+                    && jsonwebtoken::decode::<Value>(
+                        credentials,
+                        &jsonwebtoken::DecodingKey::from_secret(secret.as_bytes()),
+                        &jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256),
+                    )
+                    .is_ok()
+            }
+        }
+    }
+}
+
+// HttpCassette
+
+/// Interactions recorded by a [`DefaultHttpServer`](struct.DefaultHttpServer.html) in proxy mode.
 ///
-/// **This is supported on `feature=http` only.**
+/// It is stored as JSON.
 ///
-/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
-#[async_trait]
-pub trait HttpServer: Send + Sync {
-    /// Returns the next request received by the server.
-    ///
-    /// `None` is returned if the server is stopped.
-    async fn next(&mut self) -> Option<HttpRequest>;
+/// **This is supported on `feature=http-record` only.**
+#[cfg(feature = "http-record")]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HttpCassette {
+    pub interactions: Vec<HttpInteraction>,
+}
 
-    /// Stops the server.
-    async fn stop(self);
+#[cfg(feature = "http-record")]
+impl HttpCassette {
+    /// Loads a cassette from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json).map_err(io::Error::other)
+    }
+
+    /// Saves the cassette to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
 }
 
-// DefaultHttpServer
+// HttpCookie
 
-/// Default implementation of [`HttpServer`](trait.HttpServer.html).
+/// Cookie set by a [`HttpResponse`](enum.HttpResponse.html).
 ///
 /// **This is supported on `feature=http` only.**
-///
-/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
-pub struct DefaultHttpServer {
-    req_rx: mpsc::Receiver<HttpRequest>,
-    server: JoinHandle<()>,
-    stop_tx: oneshot::Sender<()>,
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpCookie {
+    pub domain: Option<String>,
+    pub http_only: bool,
+    pub max_age: Option<Duration>,
+    pub name: String,
+    pub path: Option<String>,
+    pub same_site: Option<HttpSameSite>,
+    pub secure: bool,
+    pub value: String,
 }
 
-impl DefaultHttpServer {
-    /// Starts a new server listening on the given address.
-    ///
-    /// The server will respond status code 200 with an empty response to all requests.
-    pub async fn start(addr: &SocketAddr) -> io::Result<Self> {
-        Self::with_response(addr, HttpResponse::Empty).await
+impl HttpCookie {
+    /// Creates a new cookie without attributes.
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> Self {
+        Self {
+            domain: None,
+            http_only: false,
+            max_age: None,
+            name: name.into(),
+            path: None,
+            same_site: None,
+            secure: false,
+            value: value.into(),
+        }
     }
 
-    /// Starts a new server listening on the given address.
-    ///
-    /// The server will respond status code 200 with the given one to all requests.
-    pub async fn with_response(addr: &SocketAddr, resp: HttpResponse) -> io::Result<Self> {
-        let (stop_tx, stop_rx) = oneshot::channel();
-        let (req_tx, req_rx) = mpsc::channel(1);
-        let app = Router::new().fallback(
-            move |method: Method,
-                  uri: Uri,
-                  Query(query): Query<Vec<(String, String)>>,
-                  headers: HeaderMap,
-                  body: Bytes| async move {
-                let mut req_headers = HashMap::new();
-                for (name, val) in headers {
-                    let name = if let Some(name) = &name {
-                        name.as_str()
-                    } else {
-                        warn!("request contains header with no name");
-                        continue;
-                    };
-                    let val = match val.to_str() {
-                        Ok(val) => val,
-                        Err(err) => {
-                            warn!(details = %err, header = name, "failed to decode header value");
-                            continue;
-                        }
-                    };
-                    req_headers.insert(name.into(), val.into());
-                }
-                let query = query.into_iter().fold(
-                    HashMap::<String, Vec<String>>::new(),
-                    |mut query, (key, val)| {
-                        query.entry(key).or_default().push(val);
-                        query
-                    },
-                );
-                let req = HttpRequest {
-                    body: body.to_vec(),
-                    headers: req_headers,
-                    method: method.to_string(),
-                    path: uri.path().into(),
-                    query,
-                };
-                req_tx.send(req).await.ok();
-                match resp {
-                    HttpResponse::Empty => StatusCode::OK.into_response(),
-                    HttpResponse::Html(html) => (StatusCode::OK, Html(html)).into_response(),
-                    HttpResponse::Json(json) => (StatusCode::OK, Json(json)).into_response(),
-                    HttpResponse::Text(text) => (StatusCode::OK, text).into_response(),
-                }
-            },
-        );
-        let server = Server::bind(addr)
-            .serve(app.into_make_service())
-            .with_graceful_shutdown(async {
-                stop_rx.await.ok();
-            });
-        let server = spawn(async {
-            if let Err(err) = server.await {
-                error!(details = %err, "failed to start server");
-            }
-        });
-        Ok(Self {
-            req_rx,
-            server,
-            stop_tx,
-        })
+    /// Sets the `Domain` attribute.
+    pub fn with_domain<D: Into<String>>(mut self, domain: D) -> Self {
+        self.domain = Some(domain.into());
+        self
     }
-}
 
-#[async_trait]
-impl HttpServer for DefaultHttpServer {
-    async fn next(&mut self) -> Option<HttpRequest> {
-        self.req_rx.recv().await
+    /// Sets the `HttpOnly` attribute.
+    pub fn with_http_only(mut self) -> Self {
+        self.http_only = true;
+        self
     }
 
-    async fn stop(self) {
-        self.stop_tx.send(()).ok();
-        if let Err(err) = self.server.await {
-            error!(details = %err, "failed to stop server");
-        }
+    /// Sets the `Max-Age` attribute.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
     }
-}
 
-// MockHttpServer
+    /// Sets the `Path` attribute.
+    pub fn with_path<P: Into<String>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
 
-#[cfg(feature = "mock")]
-mockall::mock! {
-    /// `mockall` implementation of [`HttpServer`](trait.HttpServer.html).
-    ///
-    /// **This is supported on `feature=http,mock` only.**
-    ///
-    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
-    pub HttpServer {}
+    /// Sets the `SameSite` attribute.
+    pub fn with_same_site(mut self, same_site: HttpSameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
 
-    #[async_trait]
-    impl HttpServer for HttpServer {
-        async fn next(&mut self) -> Option<HttpRequest>;
-        async fn stop(self);
+    /// Sets the `Secure` attribute.
+    pub fn with_secure(mut self) -> Self {
+        self.secure = true;
+        self
     }
 }
 
-// Tests
+impl Display for HttpCookie {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={same_site}")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        Ok(())
+    }
+}
 
-#[cfg(test)]
-mod test {
-    use std::{
-        net::{Ipv4Addr, SocketAddrV4},
-        time::Duration,
-    };
+// HttpCors
 
-    use reqwest::{Client, Response};
-    use tokio::time::sleep;
+/// CORS configuration of a server.
+///
+/// By default, all origins, methods and headers are allowed.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpCors {
+    credentials: bool,
+    expose_headers: Vec<String>,
+    headers: Vec<String>,
+    max_age: Option<Duration>,
+    methods: Vec<String>,
+    origins: Vec<String>,
+}
 
-    use super::*;
+impl HttpCors {
+    /// Creates a new configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    // Mods
+    /// Sets `Access-Control-Allow-Credentials` to `true`.
+    ///
+    /// The origin of the request is then sent back instead of `*`.
+    pub fn with_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
 
-    mod default_http_server {
-        use super::*;
+    /// Adds a header to `Access-Control-Expose-Headers`.
+    pub fn with_expose_header<H: Into<String>>(mut self, header: H) -> Self {
+        self.expose_headers.push(header.into());
+        self
+    }
 
-        // run
+    /// Allows a request header.
+    ///
+    /// If no header is allowed, the ones requested by preflights are.
+    pub fn with_header<H: Into<String>>(mut self, header: H) -> Self {
+        self.headers.push(header.into());
+        self
+    }
 
-        async fn run(port: u16, resp: HttpResponse) -> Response {
-            let expected = HttpRequest {
-                body: "abc".to_string().into_bytes(),
-                headers: HashMap::from_iter([
-                    ("accept".into(), "*/*".into()),
-                    ("content-length".into(), "3".into()),
-                    ("host".into(), format!("localhost:{port}")),
-                ]),
-                method: "GET".into(),
-                path: "/a/b".into(),
-                query: HashMap::from_iter([("foo".into(), vec!["bar1".into(), "bar2".into()])]),
-            };
-            let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port));
-            let mut server = DefaultHttpServer::with_response(&addr, resp)
-                .await
-                .expect("failed to start server");
-            sleep(Duration::from_secs(1)).await;
-            let client = Client::new();
-            let query: Vec<(String, String)> = expected
-                .query
-                .clone()
-                .into_iter()
-                .flat_map(|(key, val)| val.into_iter().map(move |val| (key.clone(), val)))
-                .collect();
-            let resp = client
-                .get(format!("http://localhost:{port}{}", expected.path))
-                .query(&query)
-                .body(expected.body.clone())
-                .send()
-                .await
-                .expect("failed to send request");
-            let status = resp.status();
-            if status != reqwest::StatusCode::OK {
-                let body = resp.text().await.expect("failed to read response body");
-                panic!("request failed with status {status}: {body}");
-            }
-            let req = server.next().await.expect("failed to receive request");
-            assert_eq!(req, expected);
-            server.stop().await;
-            client
-                .get(format!("http://localhost:{port}"))
-                .send()
-                .await
-                .expect_err("request should fail after server is stopped");
-            resp
-        }
+    /// Sets `Access-Control-Max-Age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
 
-        // Tests
+    /// Allows a method.
+    ///
+    /// If no method is allowed, the one requested by preflights is.
+    pub fn with_method<M: Into<String>>(mut self, method: M) -> Self {
+        self.methods.push(method.into().to_uppercase());
+        self
+    }
 
-        #[tokio::test]
-        async fn empty() {
-            let resp = run(8000, HttpResponse::Empty).await;
-            let text = resp.text().await.expect("failed to read response body");
-            assert!(text.is_empty());
+    /// Allows an origin (like `https://example.com`).
+    ///
+    /// If no origin is allowed, all are.
+    pub fn with_origin<O: Into<String>>(mut self, origin: O) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    #[inline]
+    fn allow_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        if self.origins.is_empty() && !self.credentials {
+            return Some(HeaderValue::from_static("*"));
         }
+        origin
+            .filter(|origin| {
+                self.origins.is_empty() || self.origins.iter().any(|allowed| allowed == *origin)
+            })
+            .cloned()
+    }
 
-        #[tokio::test]
-        async fn html() {
-            let expected = "<head></head>";
-            let resp = run(8001, HttpResponse::Html(expected.into())).await;
-            let text = resp.text().await.expect("failed to read response body");
-            assert_eq!(text, expected);
+    #[inline]
+    fn apply(&self, origin: Option<&HeaderValue>, headers: &mut HeaderMap) {
+        if let Some(origin) = self.allow_origin(origin) {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            if self.credentials {
+                headers.insert(
+                    ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+            if !self.expose_headers.is_empty() {
+                insert_joined(headers, ACCESS_CONTROL_EXPOSE_HEADERS, &self.expose_headers);
+            }
         }
+        if !self.origins.is_empty() || self.credentials {
+            headers.append(VARY, HeaderValue::from_static("origin"));
+        }
+    }
 
-        #[tokio::test]
-        async fn json() {
-            let expected = Value::String("val".into());
-            let resp = run(8002, HttpResponse::Json(expected.clone())).await;
-            let json: Value = resp.json().await.expect("failed to read response body");
-            assert_eq!(json, expected);
+    #[inline]
+    fn preflight(&self, headers: &HeaderMap) -> Response {
+        let mut resp = StatusCode::NO_CONTENT.into_response();
+        let origin = headers.get(ORIGIN);
+        if self.allow_origin(origin).is_some() {
+            let resp_headers = resp.headers_mut();
+            if self.methods.is_empty() {
+                if let Some(method) = headers.get(ACCESS_CONTROL_REQUEST_METHOD) {
+                    resp_headers.insert(ACCESS_CONTROL_ALLOW_METHODS, method.clone());
+                }
+            } else {
+                insert_joined(resp_headers, ACCESS_CONTROL_ALLOW_METHODS, &self.methods);
+            }
+            if self.headers.is_empty() {
+                if let Some(req_headers) = headers.get(ACCESS_CONTROL_REQUEST_HEADERS) {
+                    resp_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, req_headers.clone());
+                }
+            } else {
+                insert_joined(resp_headers, ACCESS_CONTROL_ALLOW_HEADERS, &self.headers);
+            }
+            if let Some(max_age) = self.max_age {
+                resp_headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+            }
+        }
+        self.apply(origin, resp.headers_mut());
+        resp
+    }
+}
+
+// HttpFault
+
+/// Fault injected in the connection instead of responding normally.
+///
+/// Faults are only injected on plain TCP connections and close the connection.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HttpFault {
+    /// Closes the connection without responding.
+    CloseWithoutResponse,
+    /// Responds with a malformed status line.
+    MalformedStatusLine,
+    /// Resets the connection once the given number of bytes of the response (status line and
+    /// headers included) were sent.
+    ///
+    /// The reset is done by closing the socket with `SO_LINGER` set to 0. On some platforms,
+    /// closing it may still block the runtime thread for a short time.
+    ResetAfter(usize),
+    /// Sends the status line, the headers and the given number of bytes of the body, then closes
+    /// the connection.
+    TruncatedBody(usize),
+}
+
+// HttpInteraction
+
+/// Request and its response recorded in a [`HttpCassette`](struct.HttpCassette.html).
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HttpInteraction {
+    pub req: HttpRequest,
+    pub resp: HttpRecordedResponse,
+}
+
+// HttpOverflowPolicy
+
+/// Policy applied when a request is received while the request channel of a server is full,
+/// because [`next`](trait.HttpServer.html#tymethod.next) isn't called fast enough.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HttpOverflowPolicy {
+    /// The response is delayed until there is room in the channel.
+    #[default]
+    Block,
+    /// The request is responded but not sent to the channel and a warning is logged.
+    Drop,
+    /// The channel grows without limit.
+    Grow,
+}
+
+// HttpPart
+
+/// Part of a `multipart/form-data` request.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpPart {
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+    pub filename: Option<String>,
+    pub name: String,
+}
+
+// HttpRecordedResponse
+
+/// Response recorded in a [`HttpCassette`](struct.HttpCassette.html).
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HttpRecordedResponse {
+    pub body: Vec<u8>,
+    pub headers: Vec<(String, String)>,
+    pub status: u16,
+}
+
+// HttpReplayRules
+
+/// Rules used to match requests with the interactions of a [`HttpCassette`](struct.HttpCassette.html).
+///
+/// Method and path are always compared.
+///
+/// **This is supported on `feature=http-record` only.**
+#[cfg(feature = "http-record")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HttpReplayRules {
+    /// Compares the bodies. Defaults to `false`.
+    pub body: bool,
+    /// Compares the query parameters. Defaults to `true`.
+    pub query: bool,
+}
+
+#[cfg(feature = "http-record")]
+impl HttpReplayRules {
+    /// Returns `true` if the request matches the recorded one.
+    pub fn matches(&self, req: &HttpRequest, recorded: &HttpRequest) -> bool {
+        req.method == recorded.method
+            && req.path == recorded.path
+            && (!self.query || req.query == recorded.query)
+            && (!self.body || req.body == recorded.body)
+    }
+}
+
+#[cfg(feature = "http-record")]
+impl Default for HttpReplayRules {
+    fn default() -> Self {
+        Self {
+            body: false,
+            query: true,
+        }
+    }
+}
+
+// HttpResponse
+
+/// HTTP response.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HttpResponse {
+    Empty,
+    Html(String),
+    Json(Value),
+    /// Empty response with the given status code.
+    Status(u16),
+    Text(String),
+    /// Response with a fault injected in the connection.
+    WithFault {
+        fault: HttpFault,
+        resp: Box<HttpResponse>,
+    },
+    /// Response with additional headers.
+    WithHeaders {
+        headers: Vec<(String, String)>,
+        resp: Box<HttpResponse>,
+    },
+}
+
+impl HttpResponse {
+    /// Adds a `Set-Cookie` header to the response.
+    pub fn with_cookie(self, cookie: HttpCookie) -> Self {
+        self.with_header("set-cookie", cookie.to_string())
+    }
+
+    /// Injects a fault in the connection when the response is sent.
+    ///
+    /// The body of the response is used by
+    /// [`HttpFault::TruncatedBody`](enum.HttpFault.html#variant.TruncatedBody).
+    pub fn with_fault(self, fault: HttpFault) -> Self {
+        Self::WithFault {
+            fault,
+            resp: Box::new(self),
+        }
+    }
+
+    /// Adds a header to the response.
+    ///
+    /// If the header is already defined, both values are sent.
+    pub fn with_header<N: Into<String>, V: Into<String>>(self, name: N, val: V) -> Self {
+        match self {
+            Self::WithHeaders { mut headers, resp } => {
+                headers.push((name.into(), val.into()));
+                Self::WithHeaders { headers, resp }
+            }
+            resp => Self::WithHeaders {
+                headers: vec![(name.into(), val.into())],
+                resp: Box::new(resp),
+            },
+        }
+    }
+
+    #[inline]
+    fn fault(&self) -> Option<HttpFault> {
+        match self {
+            Self::WithFault { fault, .. } => Some(*fault),
+            Self::WithHeaders { resp, .. } => resp.fault(),
+            _ => None,
+        }
+    }
+}
+
+// HttpSameSite
+
+/// Value of the `SameSite` attribute of a [`HttpCookie`](struct.HttpCookie.html).
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HttpSameSite {
+    Lax,
+    None,
+    Strict,
+}
+
+impl Display for HttpSameSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lax => write!(f, "Lax"),
+            Self::None => write!(f, "None"),
+            Self::Strict => write!(f, "Strict"),
+        }
+    }
+}
+
+// HttpExpectation
+
+/// Expectation of a [`DefaultHttpServer`](struct.DefaultHttpServer.html).
+///
+/// Requests matching the expectation are responded `resp`.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone)]
+pub struct HttpExpectation {
+    matcher: HttpRequestMatcher,
+    resp: HttpResponse,
+    times: Option<usize>,
+}
+
+impl HttpExpectation {
+    /// Creates a new expectation.
+    ///
+    /// By default, the request is expected at least once.
+    pub fn new(matcher: HttpRequestMatcher, resp: HttpResponse) -> Self {
+        Self {
+            matcher,
+            resp,
+            times: None,
+        }
+    }
+
+    /// Sets the exact number of times the request is expected.
+    pub fn with_times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+}
+
+// HttpRequestMatcher
+
+/// Matcher of [`HttpRequest`](struct.HttpRequest.html).
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone)]
+pub struct HttpRequestMatcher {
+    auth: Option<HttpAuth>,
+    body: Option<Arc<HttpBodyPredicate>>,
+    headers: Vec<(String, String)>,
+    method: String,
+    path: String,
+}
+
+impl HttpRequestMatcher {
+    /// Creates a new matcher of requests whose method (case insensitive) and path are equal to
+    /// the given ones.
+    ///
+    /// The path can be a template (see
+    /// [`DefaultHttpServerBuilder::route`](struct.DefaultHttpServerBuilder.html#method.route)).
+    pub fn new<M: Into<String>, P: Into<String>>(method: M, path: P) -> Self {
+        Self {
+            auth: None,
+            body: None,
+            headers: vec![],
+            method: method.into().to_uppercase(),
+            path: path.into(),
+        }
+    }
+
+    /// Matches only requests satisfying `auth`.
+    pub fn with_auth(mut self, auth: HttpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Matches only requests whose body satisfies `predicate`.
+    pub fn with_body_matching<F: Fn(&[u8]) -> bool + Send + Sync + 'static>(
+        mut self,
+        predicate: F,
+    ) -> Self {
+        self.body = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Matches only requests containing the header `name` (case insensitive) with value `val`.
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.headers.push((name.into().to_lowercase(), val.into()));
+        self
+    }
+
+    /// Returns `true` if the request matches.
+    pub fn matches(&self, req: &HttpRequest) -> bool {
+        req.method == self.method
+            && match_path(&self.path, &req.path).is_some()
+            && self
+                .headers
+                .iter()
+                .all(|(name, val)| req.headers_all(name).contains(&val.as_str()))
+            && match &self.auth {
+                Some(auth) => auth.verify(req),
+                None => true,
+            }
+            && match &self.body {
+                Some(predicate) => predicate(&req.body),
+                None => true,
+            }
+    }
+}
+
+impl Display for HttpRequestMatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.path)
+    }
+}
+
+// HttpStopSummary
+
+/// Summary of the requests handled by a server, returned when it is stopped.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HttpStopSummary {
+    /// Number of requests aborted before being responded, because the drain timeout expired or
+    /// the client closed the connection.
+    pub dropped: usize,
+    /// Number of requests responded.
+    pub served: usize,
+}
+
+// HttpServer
+
+/// Simple HTTP server that listen all requests.
+///
+/// **This is supported on `feature=http` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+#[async_trait]
+pub trait HttpServer: Send + Sync {
+    /// Returns the next request received by the server.
+    ///
+    /// `None` is returned if the server is stopped.
+    async fn next(&mut self) -> Option<HttpRequest>;
+
+    /// Stops the server.
+    async fn stop(self);
+}
+
+// DefaultHttpServer
+
+/// Default implementation of [`HttpServer`](trait.HttpServer.html).
+///
+/// **This is supported on `feature=http` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+pub struct DefaultHttpServer {
+    local_addr: Option<SocketAddr>,
+    req_rx: RequestReceiver,
+    scheme: &'static str,
+    server: JoinHandle<()>,
+    socket_path: Option<PathBuf>,
+    state: Arc<ServerState>,
+    stop_tx: oneshot::Sender<()>,
+    ws_rx: mpsc::UnboundedReceiver<WebSocketMessage>,
+}
+
+impl DefaultHttpServer {
+    /// Returns the requests rejected because they didn't satisfy the authentication of the
+    /// server.
+    ///
+    /// See [`DefaultHttpServerBuilder::auth`](struct.DefaultHttpServerBuilder.html#method.auth).
+    pub fn auth_failures(&self) -> Vec<HttpRequest> {
+        self.state
+            .auth_failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns a new builder to configure the responses of the server.
+    pub fn builder() -> DefaultHttpServerBuilder {
+        DefaultHttpServerBuilder::default()
+    }
+
+    /// Returns all requests received by the server and their responses as a
+    /// [HAR](https://w3c.github.io/web-performance/specs/HAR/Overview.html) document.
+    ///
+    /// It can be saved to a `.har` file to be inspected with the developer tools of a browser.
+    ///
+    /// # Panics
+    /// Panics if the server wasn't built with
+    /// [`DefaultHttpServerBuilder::har`](struct.DefaultHttpServerBuilder.html#method.har).
+    pub fn export_har(&self) -> Value {
+        let har = self.state.har.as_ref().expect("HAR capture is not enabled");
+        let url = self.url();
+        let entries: Vec<Value> = har
+            .exchanges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|exchange| exchange.to_har_entry(&url))
+            .collect();
+        json!({
+            "log": {
+                "creator": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": entries,
+                "version": "1.2",
+            }
+        })
+    }
+
+    /// Starts a new server listening on the given Unix socket.
+    ///
+    /// The server will respond status code 200 with an empty response to all requests.
+    ///
+    /// **This is supported on `unix` only.**
+    #[cfg(unix)]
+    pub async fn bind_uds<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        Self::builder().start_uds(path).await
+    }
+
+    /// Returns the address on which the server is listening.
+    ///
+    /// It is useful to know the port assigned by the system if the server was started on port 0.
+    ///
+    /// # Panics
+    /// Panics if the server listens on a Unix socket.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+            .expect("server doesn't listen on a TCP socket")
+    }
+
+    /// Stops the server and returns a summary of the requests it handled.
+    ///
+    /// In-flight requests are waited for up to the
+    /// [drain timeout](struct.DefaultHttpServerBuilder.html#method.drain_timeout). Then their
+    /// connections are aborted.
+    pub async fn shutdown(self) -> HttpStopSummary {
+        self.stop_tx.send(()).ok();
+        if let Err(err) = self.server.await {
+            error!(details = %err, "failed to stop server");
+        }
+        if let Some(path) = &self.socket_path {
+            if let Err(err) = fs::remove_file(path) {
+                warn!(details = %err, path = %path.display(), "failed to remove socket");
+            }
+        }
+        HttpStopSummary {
+            dropped: self.state.dropped.load(Ordering::Relaxed),
+            served: self.state.served.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the path of the Unix socket on which the server is listening.
+    ///
+    /// `None` is returned if the server listens on a TCP socket.
+    pub fn socket_path(&self) -> Option<&Path> {
+        self.socket_path.as_deref()
+    }
+
+    /// Starts a new server listening on the given address.
+    ///
+    /// The server will respond status code 200 with an empty response to all requests.
+    pub async fn start(addr: &SocketAddr) -> io::Result<Self> {
+        Self::builder().start(addr).await
+    }
+
+    /// Starts a new server listening on the given address and serving `router`.
+    ///
+    /// See [`DefaultHttpServerBuilder::router`](struct.DefaultHttpServerBuilder.html#method.router).
+    pub async fn with_router(addr: &SocketAddr, router: Router) -> io::Result<Self> {
+        Self::builder().router(router).start(addr).await
+    }
+
+    /// Starts a new server listening on the given address.
+    ///
+    /// The server will respond status code 200 with the given one to all requests.
+    pub async fn with_response(addr: &SocketAddr, resp: HttpResponse) -> io::Result<Self> {
+        Self::builder().fallback(resp).start(addr).await
+    }
+
+    /// Returns the base URL of the server (like `http://127.0.0.1:8000`).
+    ///
+    /// If the server listens on a Unix socket, `http://localhost` is returned.
+    pub fn url(&self) -> String {
+        match self.local_addr {
+            Some(addr) => format!("{}://{addr}", self.scheme),
+            None => format!("{}://localhost", self.scheme),
+        }
+    }
+
+    /// Verifies that all expectations were satisfied.
+    ///
+    /// # Panics
+    /// Panics if an expectation is not satisfied or if the server has expectations and received
+    /// requests matching none of them nor any route.
+    pub fn verify(&self) {
+        let mut errors = vec![];
+        for expectation in &self.state.expectations {
+            let calls = expectation.calls.load(Ordering::Relaxed);
+            match expectation.expectation.times {
+                Some(times) if calls != times => errors.push(format!(
+                    "`{}` was received {calls} times, expected {times}",
+                    expectation.expectation.matcher,
+                )),
+                None if calls == 0 => errors.push(format!(
+                    "`{}` was never received",
+                    expectation.expectation.matcher,
+                )),
+                _ => {}
+            }
+        }
+        let unexpected = self
+            .state
+            .unexpected
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for req in unexpected.iter() {
+            errors.push(format!("unexpected request `{} {}`", req.method, req.path));
+        }
+        if !errors.is_empty() {
+            panic!("HTTP expectations not satisfied:\n{}", errors.join("\n"));
+        }
+    }
+}
+
+#[async_trait]
+impl HttpServer for DefaultHttpServer {
+    async fn next(&mut self) -> Option<HttpRequest> {
+        self.req_rx.recv().await
+    }
+
+    async fn stop(self) {
+        self.shutdown().await;
+    }
+}
+
+// DefaultHttpServerBuilder
+
+/// Builder of [`DefaultHttpServer`](struct.DefaultHttpServer.html).
+///
+/// **This is supported on `feature=http` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+#[derive(Clone)]
+pub struct DefaultHttpServerBuilder {
+    auth: Option<HttpAuth>,
+    capacity: usize,
+    compression: bool,
+    cors: Option<HttpCors>,
+    drain_timeout: Duration,
+    expectations: Vec<HttpExpectation>,
+    fallback: HttpResponse,
+    faults: Vec<(usize, HttpFault)>,
+    har: Option<usize>,
+    overflow_policy: HttpOverflowPolicy,
+    router: Option<Router>,
+    routes: Vec<HttpRoute>,
+    #[cfg(feature = "http-tls")]
+    tls: Option<TlsConfig>,
+    #[cfg(feature = "http-record")]
+    vcr: Option<VcrConfig>,
+    ws_paths: Vec<String>,
+}
+
+impl DefaultHttpServerBuilder {
+    /// Requires all requests to satisfy `auth`.
+    ///
+    /// Other requests are responded status code 401 with a `WWW-Authenticate` header. They are
+    /// still returned by [`next`](trait.HttpServer.html#tymethod.next) and can be retrieved with
+    /// [`DefaultHttpServer::auth_failures`](struct.DefaultHttpServer.html#method.auth_failures).
+    pub fn auth(mut self, auth: HttpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sets the capacity of the channel of requests returned by
+    /// [`next`](trait.HttpServer.html#tymethod.next).
+    ///
+    /// When it is full, the [overflow policy](#method.overflow_policy) is applied. By default, it
+    /// is 1.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0.
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "channel capacity must be greater than 0");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Compresses the responses with `br` or `gzip` according to the `Accept-Encoding` header of
+    /// the requests.
+    ///
+    /// Request bodies are always decompressed.
+    pub fn compression(mut self) -> Self {
+        self.compression = true;
+        self
+    }
+
+    /// Answers CORS preflights (`OPTIONS` requests with an `Access-Control-Request-Method` header)
+    /// according to `cors` and adds the CORS headers to the other responses.
+    ///
+    /// Preflights are not returned by [`next`](trait.HttpServer.html#tymethod.next). By default,
+    /// they are handled like any request.
+    pub fn cors(mut self, cors: HttpCors) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Sets how long [`stop`](trait.HttpServer.html#tymethod.stop) waits for in-flight requests
+    /// before aborting their connections.
+    ///
+    /// By default, it is 5 seconds.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Adds an expectation.
+    ///
+    /// Expectations take precedence over routes. If several expectations match, the first one is
+    /// used. See [`DefaultHttpServer::verify`](struct.DefaultHttpServer.html#method.verify).
+    pub fn expect(mut self, expectation: HttpExpectation) -> Self {
+        self.expectations.push(expectation);
+        self
+    }
+
+    /// Sets the response to requests that match no expectation nor route.
+    ///
+    /// For example, [`HttpResponse::Status(404)`](enum.HttpResponse.html#variant.Status) can be
+    /// used to reject unexpected requests.
+    ///
+    /// By default, it is [`HttpResponse::Empty`](enum.HttpResponse.html#variant.Empty).
+    pub fn fallback(mut self, resp: HttpResponse) -> Self {
+        self.fallback = resp;
+        self
+    }
+
+    /// Injects `fault` when responding to the `n`-th request (starting from 1), whatever its
+    /// response is.
+    pub fn fault_on_request(mut self, n: usize, fault: HttpFault) -> Self {
+        self.faults.push((n, fault));
+        self
+    }
+
+    /// Captures the requests and their responses to export them with
+    /// [`DefaultHttpServer::export_har`](struct.DefaultHttpServer.html#method.export_har).
+    ///
+    /// Only the last `capacity` exchanges are kept. Response bodies are copied as they are sent,
+    /// so streamed responses are captured up to where the client stopped reading them.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0.
+    pub fn har(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "HAR capacity must be greater than 0");
+        self.har = Some(capacity);
+        self
+    }
+
+    /// Sets the policy applied when the channel of requests is full.
+    ///
+    /// By default, it is [`HttpOverflowPolicy::Block`](enum.HttpOverflowPolicy.html#variant.Block).
+    pub fn overflow_policy(mut self, policy: HttpOverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Adds a route.
+    ///
+    /// Requests whose method (case insensitive) and path are equal to the given ones are responded
+    /// `resp`. If several routes match, the first one is used.
+    ///
+    /// The path can be a template whose segments starting with `:` match any non-empty segment
+    /// (like `/users/:id`). They are exposed in
+    /// [`HttpRequest::path_params`](struct.HttpRequest.html#structfield.path_params).
+    pub fn route<M: Into<String>, P: Into<String>>(
+        mut self,
+        method: M,
+        path: P,
+        resp: HttpResponse,
+    ) -> Self {
+        self.routes.push(HttpRoute {
+            method: method.into().to_uppercase(),
+            path: path.into(),
+            resp,
+        });
+        self
+    }
+
+    /// Serves `router` instead of the configured responses.
+    ///
+    /// Every request is still returned by [`next`](trait.HttpServer.html#tymethod.next), once
+    /// `router` responded it. Expectations, routes, the fallback, faults, authentication,
+    /// cassettes and WebSocket paths are ignored.
+    pub fn router(mut self, router: Router) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// Proxies requests that match no expectation nor route to `upstream` (like
+    /// `https://api.example.com`) and records the interactions in the cassette file.
+    ///
+    /// The cassette is saved after each interaction. It can be replayed with
+    /// [`replay`](#method.replay).
+    ///
+    /// **This is supported on `feature=http-record` only.**
+    #[cfg(feature = "http-record")]
+    pub fn record<U: Into<String>, P: Into<PathBuf>>(mut self, upstream: U, cassette: P) -> Self {
+        self.vcr = Some(VcrConfig::Record {
+            cassette: cassette.into(),
+            upstream: upstream.into(),
+        });
+        self
+    }
+
+    /// Responds to requests that match no expectation nor route with the interactions of the
+    /// cassette file.
+    ///
+    /// If several interactions match, they are used in the order in which they were recorded, the
+    /// last one being repeated. If none matches, the fallback response is used.
+    ///
+    /// **This is supported on `feature=http-record` only.**
+    #[cfg(feature = "http-record")]
+    pub fn replay<P: Into<PathBuf>>(mut self, cassette: P, rules: HttpReplayRules) -> Self {
+        self.vcr = Some(VcrConfig::Replay {
+            cassette: cassette.into(),
+            rules,
+        });
+        self
+    }
+
+    /// Serves HTTPS with the given PEM-encoded certificate chain and private key.
+    ///
+    /// HTTP/1.1 and HTTP/2 are negotiated with ALPN.
+    ///
+    /// **This is supported on `feature=http-tls` only.**
+    #[cfg(feature = "http-tls")]
+    pub fn tls<C: Into<Vec<u8>>, K: Into<Vec<u8>>>(mut self, cert: C, key: K) -> Self {
+        self.tls = Some(TlsConfig {
+            cert: cert.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    /// Accepts WebSocket connections on `path`.
+    ///
+    /// Upgrade requests are not returned by [`next`](trait.HttpServer.html#tymethod.next). See
+    /// [`WebSocketServer`](trait.WebSocketServer.html).
+    pub fn websocket<P: Into<String>>(mut self, path: P) -> Self {
+        self.ws_paths.push(path.into());
+        self
+    }
+
+    /// Starts a new server listening on the given address.
+    ///
+    /// If the port is 0, a free one is assigned by the system. It can be retrieved with
+    /// [`local_addr`](struct.DefaultHttpServer.html#method.local_addr).
+    ///
+    /// Both HTTP/1.1 and HTTP/2 (with prior knowledge) are supported.
+    ///
+    /// If the server replays a cassette that can't be loaded, an error is returned.
+    pub async fn start(self, addr: &SocketAddr) -> io::Result<DefaultHttpServer> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        #[cfg(feature = "http-tls")]
+        let acceptor = self.tls.as_ref().map(TlsConfig::acceptor).transpose()?;
+        #[cfg(feature = "http-tls")]
+        let scheme = if acceptor.is_some() { "https" } else { "http" };
+        #[cfg(not(feature = "http-tls"))]
+        let scheme = "http";
+        self.launch(
+            Some(local_addr),
+            None,
+            scheme,
+            move |app, stop_rx, drain_timeout| {
+                #[cfg(feature = "http-tls")]
+                if let Some(acceptor) = acceptor {
+                    return serve_tls(listener, acceptor, app, stop_rx, drain_timeout);
+                }
+                serve(listener, app, stop_rx, drain_timeout)
+            },
+        )
+    }
+
+    /// Starts a new server listening on the given Unix socket.
+    ///
+    /// The socket file is removed when the server is stopped.
+    ///
+    /// **This is supported on `unix` only.**
+    #[cfg(unix)]
+    pub async fn start_uds<P: Into<PathBuf>>(self, path: P) -> io::Result<DefaultHttpServer> {
+        let path = path.into();
+        let listener = UnixListener::bind(&path)?;
+        let res = self.launch(
+            None,
+            Some(path.clone()),
+            "http",
+            move |app, stop_rx, drain_timeout| serve_uds(listener, app, stop_rx, drain_timeout),
+        );
+        if res.is_err() {
+            fs::remove_file(&path).ok();
+        }
+        res
+    }
+
+    fn launch<F: FnOnce(Router, oneshot::Receiver<()>, Duration) -> JoinHandle<()>>(
+        self,
+        local_addr: Option<SocketAddr>,
+        socket_path: Option<PathBuf>,
+        scheme: &'static str,
+        run: F,
+    ) -> io::Result<DefaultHttpServer> {
+        #[cfg(feature = "http-record")]
+        let vcr = match self.vcr {
+            Some(VcrConfig::Record { cassette, upstream }) => Some(Vcr::Record {
+                cassette,
+                client: reqwest::Client::new(),
+                recorded: Default::default(),
+                upstream: upstream.trim_end_matches('/').into(),
+            }),
+            Some(VcrConfig::Replay { cassette, rules }) => {
+                let cassette = HttpCassette::load(cassette)?;
+                Some(Vcr::Replay {
+                    used: Mutex::new(vec![false; cassette.interactions.len()]),
+                    cassette,
+                    rules,
+                })
+            }
+            None => None,
+        };
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let (req_tx, req_rx) = match self.overflow_policy {
+            HttpOverflowPolicy::Block | HttpOverflowPolicy::Drop => {
+                let (tx, rx) = mpsc::channel(self.capacity);
+                let tx = RequestSender::Bounded {
+                    drop: self.overflow_policy == HttpOverflowPolicy::Drop,
+                    tx,
+                };
+                (tx, RequestReceiver::Bounded(rx))
+            }
+            HttpOverflowPolicy::Grow => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                (RequestSender::Unbounded(tx), RequestReceiver::Unbounded(rx))
+            }
+        };
+        let (ws_tx, ws_rx) = mpsc::unbounded_channel();
+        let (ws_out_tx, _) = broadcast::channel(16);
+        let state = Arc::new(ServerState {
+            auth: self.auth,
+            auth_failures: Default::default(),
+            compression: self.compression,
+            cors: self.cors,
+            dropped: AtomicUsize::new(0),
+            expectations: self
+                .expectations
+                .into_iter()
+                .map(|expectation| ExpectationState {
+                    calls: AtomicUsize::new(0),
+                    expectation,
+                })
+                .collect(),
+            fallback: self.fallback,
+            faults: self.faults,
+            har: self.har.map(|capacity| HarRecorder {
+                capacity,
+                exchanges: Mutex::new(VecDeque::with_capacity(capacity)),
+            }),
+            req_tx,
+            requests: AtomicUsize::new(0),
+            routes: self.routes,
+            served: AtomicUsize::new(0),
+            unexpected: Default::default(),
+            #[cfg(feature = "http-record")]
+            vcr,
+            ws_out_tx,
+            ws_paths: self.ws_paths,
+            ws_tx,
+        });
+        let app = match self.router {
+            Some(router) => {
+                let state = state.clone();
+                router.layer(middleware::from_fn(move |req: Request, next: Next| {
+                    let state = state.clone();
+                    async move { state.record(req, next).await }
+                }))
+            }
+            None => Router::new().fallback({
+                let state = state.clone();
+                move |method: Method,
+                      uri: Uri,
+                      Query(query): Query<Vec<(String, String)>>,
+                      headers: HeaderMap,
+                      ws: Option<WebSocketUpgrade>,
+                      fault_slot: Option<Extension<FaultSlot>>,
+                      body: Bytes| {
+                    let state = state.clone();
+                    let fault_slot = fault_slot.map(|Extension(slot)| slot);
+                    async move {
+                        state
+                            .handle(method, uri, query, headers, ws, fault_slot, body)
+                            .await
+                    }
+                }
+            }),
+        };
+        let app = if state.har.is_some() {
+            app.layer(middleware::from_fn({
+                let state = state.clone();
+                move |req: Request, next: Next| {
+                    let state = state.clone();
+                    async move { state.track(req, next).await }
+                }
+            }))
+        } else {
+            app
+        };
+        let server = run(app, stop_rx, self.drain_timeout);
+        Ok(DefaultHttpServer {
+            local_addr,
+            req_rx,
+            scheme,
+            server,
+            socket_path,
+            state,
+            stop_tx,
+            ws_rx,
+        })
+    }
+}
+
+impl Default for DefaultHttpServerBuilder {
+    fn default() -> Self {
+        Self {
+            auth: None,
+            capacity: 1,
+            compression: false,
+            cors: None,
+            drain_timeout: Duration::from_secs(5),
+            expectations: vec![],
+            fallback: HttpResponse::Empty,
+            faults: vec![],
+            har: None,
+            overflow_policy: HttpOverflowPolicy::Block,
+            router: None,
+            routes: vec![],
+            #[cfg(feature = "http-tls")]
+            tls: None,
+            #[cfg(feature = "http-record")]
+            vcr: None,
+            ws_paths: vec![],
+        }
+    }
+}
+
+// ExpectationState
+
+struct ExpectationState {
+    calls: AtomicUsize,
+    expectation: HttpExpectation,
+}
+
+// FaultSlot
+
+#[derive(Clone, Default)]
+struct FaultSlot(Arc<Mutex<FaultState>>);
+
+impl FaultSlot {
+    #[inline]
+    fn arm(&self, fault: HttpFault) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = FaultState {
+            fault: Some(fault),
+            ..Default::default()
+        };
+    }
+}
+
+// FaultState
+
+#[derive(Default)]
+struct FaultState {
+    fault: Option<HttpFault>,
+    head_len: Option<usize>,
+    sent: usize,
+    tail: Vec<u8>,
+}
+
+// FaultyStream
+
+struct FaultyStream {
+    inner: TcpStream,
+    slot: FaultSlot,
+}
+
+impl AsyncRead for FaultyStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for FaultyStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let mut state = this.slot.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let limit = match state.fault {
+            None => return Pin::new(&mut this.inner).poll_write(cx, buf),
+            Some(HttpFault::CloseWithoutResponse) => {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+            }
+            Some(HttpFault::MalformedStatusLine) => {
+                while state.sent < MALFORMED_RESPONSE.len() {
+                    match Pin::new(&mut this.inner)
+                        .poll_write(cx, &MALFORMED_RESPONSE[state.sent..])
+                    {
+                        Poll::Ready(Ok(0)) => {
+                            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                        }
+                        Poll::Ready(Ok(written)) => state.sent += written,
+                        poll => return poll,
+                    }
+                }
+                return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+            }
+            Some(HttpFault::ResetAfter(n)) => n,
+            Some(HttpFault::TruncatedBody(n)) => {
+                if state.head_len.is_none() {
+                    let data = [state.tail.as_slice(), buf].concat();
+                    if let Some(pos) = find_bytes(&data, b"\r\n\r\n") {
+                        state.head_len = Some(state.sent + pos + 4 - state.tail.len());
+                    }
+                }
+                state
+                    .head_len
+                    .map(|head_len| head_len + n)
+                    .unwrap_or(usize::MAX)
+            }
+        };
+        let allowed = limit.saturating_sub(state.sent).min(buf.len());
+        if allowed == 0 {
+            if matches!(state.fault, Some(HttpFault::ResetAfter(_))) {
+                // A zero linger makes the close send a RST instead of a FIN.
+                if let Err(err) = SockRef::from(&this.inner).set_linger(Some(Duration::ZERO)) {
+                    warn!(details = %err, "failed to set linger");
+                }
+            }
+            return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+        }
+        let poll = Pin::new(&mut this.inner).poll_write(cx, &buf[..allowed]);
+        if let Poll::Ready(Ok(written)) = poll {
+            if state.head_len.is_none() {
+                let data = [state.tail.as_slice(), &buf[..written]].concat();
+                state.tail = data[data.len().saturating_sub(3)..].to_vec();
+            }
+            state.sent += written;
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+// HttpRoute
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct HttpRoute {
+    method: String,
+    path: String,
+    resp: HttpResponse,
+}
+
+// HttpExchange
+
+struct HttpExchange {
+    duration: Duration,
+    interaction: HttpInteraction,
+    query: Option<String>,
+    started: DateTime<Utc>,
+}
+
+impl HttpExchange {
+    fn to_har_entry(&self, base_url: &str) -> Value {
+        let req = &self.interaction.req;
+        let resp = &self.interaction.resp;
+        let url = match &self.query {
+            Some(query) => format!("{base_url}{}?{query}", req.path),
+            None => format!("{base_url}{}", req.path),
+        };
+        let req_headers: Vec<Value> = req
+            .raw_headers
+            .iter()
+            .map(|(name, val)| json!({ "name": name, "value": String::from_utf8_lossy(val) }))
+            .collect();
+        let query: Vec<Value> = self
+            .query
+            .as_deref()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .map(|(name, val)| json!({ "name": name, "value": val }))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let resp_headers: Vec<Value> = resp
+            .headers
+            .iter()
+            .map(|(name, val)| json!({ "name": name, "value": val }))
+            .collect();
+        let mut content = har_content(&resp.body);
+        content["mimeType"] = header_value(&resp.headers, "content-type").into();
+        let time = self.duration.as_secs_f64() * 1000.0;
+        let mut har_req = json!({
+            "bodySize": req.body.len(),
+            "cookies": [],
+            "headers": req_headers,
+            "headersSize": -1,
+            "httpVersion": "HTTP/1.1",
+            "method": req.method,
+            "queryString": query,
+            "url": url,
+        });
+        if !req.body.is_empty() {
+            let mut post_data = har_content(&req.body);
+            post_data["mimeType"] = req.header("content-type").unwrap_or_default().into();
+            har_req["postData"] = post_data;
+        }
+        json!({
+            "cache": {},
+            "request": har_req,
+            "response": {
+                "bodySize": resp.body.len(),
+                "content": content,
+                "cookies": [],
+                "headers": resp_headers,
+                "headersSize": -1,
+                "httpVersion": "HTTP/1.1",
+                "redirectURL": header_value(&resp.headers, "location"),
+                "status": resp.status,
+                "statusText": StatusCode::from_u16(resp.status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or_default(),
+            },
+            "startedDateTime": self.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "time": time,
+            "timings": {
+                "receive": 0,
+                "send": 0,
+                "wait": time,
+            },
+        })
+    }
+}
+
+// HarBody
+
+struct HarBody {
+    exchange: Option<HttpExchange>,
+    inner: Body,
+    start: Instant,
+    state: Arc<ServerState>,
+}
+
+impl HarBody {
+    fn new(inner: Body, exchange: HttpExchange, start: Instant, state: Arc<ServerState>) -> Self {
+        let mut body = Self {
+            exchange: Some(exchange),
+            inner,
+            start,
+            state,
+        };
+        if body.inner.is_end_stream() {
+            body.finish();
+        }
+        body
+    }
+
+    fn finish(&mut self) {
+        if let Some(mut exchange) = self.exchange.take() {
+            exchange.duration = self.start.elapsed();
+            if let Some(har) = &self.state.har {
+                har.push(exchange);
+            }
+        }
+    }
+}
+
+impl http_body::Body for HarBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_frame(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(exchange)) = (frame.data_ref(), &mut this.exchange) {
+                    exchange.interaction.resp.body.extend_from_slice(data);
+                }
+                if this.inner.is_end_stream() {
+                    this.finish();
+                }
+            }
+            Poll::Ready(_) => this.finish(),
+            Poll::Pending => {}
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for HarBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+// HarRecorder
+
+struct HarRecorder {
+    capacity: usize,
+    exchanges: Mutex<VecDeque<HttpExchange>>,
+}
+
+impl HarRecorder {
+    fn push(&self, exchange: HttpExchange) {
+        let mut exchanges = self
+            .exchanges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if exchanges.len() == self.capacity {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+    }
+}
+
+// InFlightRequest
+
+struct InFlightRequest {
+    completed: bool,
+    state: Arc<ServerState>,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        let counter = if self.completed {
+            &self.state.served
+        } else {
+            &self.state.dropped
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// RequestReceiver
+
+enum RequestReceiver {
+    Bounded(mpsc::Receiver<HttpRequest>),
+    Unbounded(mpsc::UnboundedReceiver<HttpRequest>),
+}
+
+impl RequestReceiver {
+    #[inline]
+    async fn recv(&mut self) -> Option<HttpRequest> {
+        match self {
+            Self::Bounded(rx) => rx.recv().await,
+            Self::Unbounded(rx) => rx.recv().await,
+        }
+    }
+}
+
+// RequestSender
+
+enum RequestSender {
+    Bounded {
+        drop: bool,
+        tx: mpsc::Sender<HttpRequest>,
+    },
+    Unbounded(mpsc::UnboundedSender<HttpRequest>),
+}
+
+impl RequestSender {
+    #[inline]
+    async fn send(&self, req: HttpRequest) {
+        match self {
+            Self::Bounded { drop: true, tx } => {
+                if let Err(mpsc::error::TrySendError::Full(req)) = tx.try_send(req) {
+                    warn!(
+                        method = %req.method,
+                        path = %req.path,
+                        "request channel is full, request dropped"
+                    );
+                }
+            }
+            Self::Bounded { drop: false, tx } => {
+                tx.send(req).await.ok();
+            }
+            Self::Unbounded(tx) => {
+                tx.send(req).ok();
+            }
+        }
+    }
+}
+
+// ServerState
+
+struct ServerState {
+    auth: Option<HttpAuth>,
+    auth_failures: Mutex<Vec<HttpRequest>>,
+    compression: bool,
+    cors: Option<HttpCors>,
+    dropped: AtomicUsize,
+    expectations: Vec<ExpectationState>,
+    fallback: HttpResponse,
+    faults: Vec<(usize, HttpFault)>,
+    har: Option<HarRecorder>,
+    req_tx: RequestSender,
+    requests: AtomicUsize,
+    routes: Vec<HttpRoute>,
+    served: AtomicUsize,
+    unexpected: Mutex<Vec<HttpRequest>>,
+    #[cfg(feature = "http-record")]
+    vcr: Option<Vcr>,
+    ws_out_tx: broadcast::Sender<WebSocketMessage>,
+    ws_paths: Vec<String>,
+    ws_tx: mpsc::UnboundedSender<WebSocketMessage>,
+}
+
+impl ServerState {
+    #[allow(clippy::too_many_arguments)]
+    async fn handle(
+        self: Arc<Self>,
+        method: Method,
+        uri: Uri,
+        query: Vec<(String, String)>,
+        headers: HeaderMap,
+        ws: Option<WebSocketUpgrade>,
+        fault_slot: Option<FaultSlot>,
+        body: Bytes,
+    ) -> Response {
+        let mut in_flight = InFlightRequest {
+            completed: false,
+            state: self.clone(),
+        };
+        let cors = self.cors.clone();
+        if let Some(cors) = &cors {
+            if method == Method::OPTIONS && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+                in_flight.completed = true;
+                return cors.preflight(&headers);
+            }
+        }
+        let encoding = headers
+            .get(ACCEPT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .filter(|_| self.compression)
+            .and_then(negotiate_encoding);
+        let origin = headers.get(ORIGIN).cloned();
+        let mut resp = self
+            .handle_request(method, uri, query, headers, ws, fault_slot, body)
+            .await;
+        if let Some(cors) = &cors {
+            cors.apply(origin.as_ref(), resp.headers_mut());
+        }
+        let resp = match encoding {
+            Some(encoding) => compress_response(resp, encoding).await,
+            None => resp,
+        };
+        in_flight.completed = true;
+        resp
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_request(
+        self: Arc<Self>,
+        method: Method,
+        uri: Uri,
+        query: Vec<(String, String)>,
+        headers: HeaderMap,
+        ws: Option<WebSocketUpgrade>,
+        fault_slot: Option<FaultSlot>,
+        body: Bytes,
+    ) -> Response {
+        let unauthorized = self.auth.as_ref().filter(|auth| {
+            !headers
+                .get(AUTHORIZATION)
+                .and_then(|val| val.to_str().ok())
+                .map(|val| auth.verify_header(val))
+                .unwrap_or_default()
+        });
+        if let Some(ws) = ws {
+            if unauthorized.is_none() && self.ws_paths.iter().any(|path| path == uri.path()) {
+                return ws.on_upgrade(move |socket| self.handle_ws(socket));
+            }
+        }
+        let mut req = match build_request(&method, &uri, query, &headers, &body) {
+            Ok(req) => req,
+            Err(err) => {
+                warn!(details = %err, "failed to decompress request body");
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        };
+        if let Some(auth) = unauthorized {
+            self.auth_failures
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(req.clone());
+            self.req_tx.send(req).await;
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(WWW_AUTHENTICATE, auth.challenge())],
+            )
+                .into_response();
+        }
+        let expectation = self
+            .expectations
+            .iter()
+            .find(|expectation| expectation.expectation.matcher.matches(&req));
+        let route = self.routes.iter().find(|route| {
+            route.method == req.method && match_path(&route.path, &req.path).is_some()
+        });
+        let n = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fault = self
+            .faults
+            .iter()
+            .find(|(fault_n, _)| *fault_n == n)
+            .map(|(_, fault)| *fault);
+        let resp = match (expectation, route) {
+            (Some(expectation), _) => {
+                expectation.calls.fetch_add(1, Ordering::Relaxed);
+                req.path_params = match_path(&expectation.expectation.matcher.path, &req.path)
+                    .unwrap_or_default();
+                fault = fault.or(expectation.expectation.resp.fault());
+                to_axum_response(expectation.expectation.resp.clone())
+            }
+            (None, Some(route)) => {
+                req.path_params = match_path(&route.path, &req.path).unwrap_or_default();
+                fault = fault.or(route.resp.fault());
+                to_axum_response(route.resp.clone())
+            }
+            (None, None) => {
+                if !self.expectations.is_empty() {
+                    self.unexpected
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(req.clone());
+                }
+                self.vcr_response(&req, &uri).await.unwrap_or_else(|| {
+                    fault = fault.or(self.fallback.fault());
+                    to_axum_response(self.fallback.clone())
+                })
+            }
+        };
+        if let Some(fault) = fault {
+            match fault_slot {
+                Some(slot) => slot.arm(fault),
+                None => warn!("faults are only injected on plain TCP connections"),
+            }
+        }
+        self.req_tx.send(req).await;
+        resp
+    }
+
+    async fn record(self: Arc<Self>, req: Request, next: Next) -> Response {
+        let mut in_flight = InFlightRequest {
+            completed: false,
+            state: self.clone(),
+        };
+        let (parts, body) = req.into_parts();
+        let body = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(err) => {
+                warn!(details = %err, "failed to read request body");
+                in_flight.completed = true;
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        };
+        let query = parts
+            .uri
+            .query()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let req = build_request(&parts.method, &parts.uri, query, &parts.headers, &body);
+        let resp = next.run(Request::from_parts(parts, Body::from(body))).await;
+        match req {
+            Ok(req) => self.req_tx.send(req).await,
+            Err(err) => warn!(details = %err, "failed to decompress request body"),
+        }
+        in_flight.completed = true;
+        resp
+    }
+
+    async fn track(self: Arc<Self>, req: Request, next: Next) -> Response {
+        let started = Utc::now();
+        let start = Instant::now();
+        let (parts, body) = req.into_parts();
+        let body = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(err) => {
+                warn!(details = %err, "failed to read request body");
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        };
+        let query = parts
+            .uri
+            .query()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let req = build_request(&parts.method, &parts.uri, query, &parts.headers, &body)
+            .unwrap_or_else(|_| HttpRequest {
+                body: body.to_vec(),
+                content_encoding: None,
+                headers: HashMap::new(),
+                method: parts.method.to_string(),
+                path: parts.uri.path().into(),
+                path_params: HashMap::new(),
+                query: HashMap::new(),
+                raw_headers: vec![],
+            });
+        let query = parts.uri.query().map(String::from);
+        let resp = next.run(Request::from_parts(parts, Body::from(body))).await;
+        let (parts, body) = resp.into_parts();
+        let resp = HttpRecordedResponse {
+            body: vec![],
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, val)| {
+                    val.to_str()
+                        .ok()
+                        .map(|val| (name.as_str().into(), val.into()))
+                })
+                .collect(),
+            status: parts.status.as_u16(),
+        };
+        let body = HarBody::new(
+            body,
+            HttpExchange {
+                duration: Duration::ZERO,
+                interaction: HttpInteraction { req, resp },
+                query,
+                started,
+            },
+            start,
+            self,
+        );
+        Response::from_parts(parts, Body::new(body))
+    }
+
+    #[cfg(feature = "http-record")]
+    #[inline]
+    async fn vcr_response(&self, req: &HttpRequest, uri: &Uri) -> Option<Response> {
+        match &self.vcr {
+            Some(vcr) => vcr.respond(req, uri.query()).await,
+            None => None,
+        }
+    }
+
+    #[cfg(not(feature = "http-record"))]
+    #[inline]
+    async fn vcr_response(&self, _req: &HttpRequest, _uri: &Uri) -> Option<Response> {
+        None
+    }
+
+    async fn handle_ws(self: Arc<Self>, mut socket: WebSocket) {
+        let mut out_rx = self.ws_out_tx.subscribe();
+        loop {
+            tokio::select! {
+                msg = socket.recv() => {
+                    let msg = match msg {
+                        Some(Ok(Message::Binary(data))) => WebSocketMessage::Binary(data),
+                        Some(Ok(Message::Close(frame))) => {
+                            let (code, reason) = frame
+                                .map(|frame| (frame.code, frame.reason.into_owned()))
+                                .unwrap_or((1005, String::new()));
+                            self.ws_tx.send(WebSocketMessage::Close { code, reason }).ok();
+                            break;
+                        }
+                        Some(Ok(Message::Text(text))) => WebSocketMessage::Text(text),
+                        Some(Ok(_)) => continue,
+                        Some(Err(err)) => {
+                            warn!(details = %err, "failed to receive websocket message");
+                            break;
+                        }
+                        None => break,
+                    };
+                    self.ws_tx.send(msg).ok();
+                }
+                msg = out_rx.recv() => {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(broadcast::error::RecvError::Lagged(count)) => {
+                            warn!(count, "websocket messages were dropped");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let close = matches!(msg, WebSocketMessage::Close { .. });
+                    let msg = match msg {
+                        WebSocketMessage::Binary(data) => Message::Binary(data),
+                        WebSocketMessage::Close { code, reason } => Message::Close(Some(CloseFrame {
+                            code,
+                            reason: reason.into(),
+                        })),
+                        WebSocketMessage::Text(text) => Message::Text(text),
+                    };
+                    if let Err(err) = socket.send(msg).await {
+                        warn!(details = %err, "failed to send websocket message");
+                        break;
+                    }
+                    if close {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// TlsConfig
+
+#[cfg(feature = "http-tls")]
+#[derive(Clone)]
+struct TlsConfig {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "http-tls")]
+impl TlsConfig {
+    fn acceptor(&self) -> io::Result<TlsAcceptor> {
+        let certs = rustls_pemfile::certs(&mut self.cert.as_slice()).collect::<io::Result<_>>()?;
+        let key = rustls_pemfile::private_key(&mut self.key.as_slice())?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no private key found"))?;
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(io::Error::other)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+// Vcr
+
+#[cfg(feature = "http-record")]
+enum Vcr {
+    Record {
+        cassette: PathBuf,
+        client: reqwest::Client,
+        recorded: Mutex<HttpCassette>,
+        upstream: String,
+    },
+    Replay {
+        cassette: HttpCassette,
+        rules: HttpReplayRules,
+        used: Mutex<Vec<bool>>,
+    },
+}
+
+#[cfg(feature = "http-record")]
+impl Vcr {
+    async fn respond(&self, req: &HttpRequest, query: Option<&str>) -> Option<Response> {
+        match self {
+            Self::Record {
+                cassette,
+                client,
+                recorded,
+                upstream,
+            } => {
+                let resp = match proxy(client, upstream, req, query).await {
+                    Ok(resp) => resp,
+                    Err(err) => {
+                        warn!(details = %err, "failed to proxy request");
+                        return Some(StatusCode::BAD_GATEWAY.into_response());
+                    }
+                };
+                let mut recorded = recorded.lock().unwrap_or_else(PoisonError::into_inner);
+                recorded.interactions.push(HttpInteraction {
+                    req: req.clone(),
+                    resp: resp.clone(),
+                });
+                if let Err(err) = recorded.save(cassette) {
+                    warn!(details = %err, "failed to save cassette");
+                }
+                Some(recorded_to_axum_response(resp))
+            }
+            Self::Replay {
+                cassette,
+                rules,
+                used,
+            } => {
+                let mut used = used.lock().unwrap_or_else(PoisonError::into_inner);
+                let matching: Vec<usize> = cassette
+                    .interactions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, interaction)| rules.matches(req, &interaction.req))
+                    .map(|(idx, _)| idx)
+                    .collect();
+                let idx = matching
+                    .iter()
+                    .find(|idx| !used[**idx])
+                    .or_else(|| matching.last())
+                    .copied()?;
+                used[idx] = true;
+                Some(recorded_to_axum_response(
+                    cassette.interactions[idx].resp.clone(),
+                ))
+            }
+        }
+    }
+}
+
+// VcrConfig
+
+#[cfg(feature = "http-record")]
+#[derive(Clone)]
+enum VcrConfig {
+    Record {
+        cassette: PathBuf,
+        upstream: String,
+    },
+    Replay {
+        cassette: PathBuf,
+        rules: HttpReplayRules,
+    },
+}
+
+// WebSocketMessage
+
+/// WebSocket message.
+///
+/// **This is supported on `feature=http` only.**
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WebSocketMessage {
+    Binary(Vec<u8>),
+    /// Close frame.
+    ///
+    /// If the client closes the connection without status code, `code` is 1005.
+    Close {
+        code: u16,
+        reason: String,
+    },
+    Text(String),
+}
+
+// WebSocketServer
+
+/// WebSocket server.
+///
+/// **This is supported on `feature=http` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+#[async_trait]
+pub trait WebSocketServer: Send + Sync {
+    /// Returns the next message received from any client.
+    ///
+    /// `None` is returned if the server is stopped.
+    async fn next_message(&mut self) -> Option<WebSocketMessage>;
+
+    /// Sends a message to all connected clients and returns their number.
+    ///
+    /// If the message is a close frame, the connections are closed.
+    fn send(&self, msg: WebSocketMessage) -> usize;
+}
+
+#[async_trait]
+impl WebSocketServer for DefaultHttpServer {
+    async fn next_message(&mut self) -> Option<WebSocketMessage> {
+        self.ws_rx.recv().await
+    }
+
+    fn send(&self, msg: WebSocketMessage) -> usize {
+        self.state.ws_out_tx.send(msg).unwrap_or(0)
+    }
+}
+
+// MockHttpServer
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`HttpServer`](trait.HttpServer.html).
+    ///
+    /// **This is supported on `feature=http,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+    pub HttpServer {}
+
+    #[async_trait]
+    impl HttpServer for HttpServer {
+        async fn next(&mut self) -> Option<HttpRequest>;
+        async fn stop(self);
+    }
+}
+
+// MockWebSocketServer
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`WebSocketServer`](trait.WebSocketServer.html).
+    ///
+    /// **This is supported on `feature=http,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http.rs).
+    pub WebSocketServer {}
+
+    #[async_trait]
+    impl WebSocketServer for WebSocketServer {
+        async fn next_message(&mut self) -> Option<WebSocketMessage>;
+        fn send(&self, msg: WebSocketMessage) -> usize;
+    }
+}
+
+// Functions
+
+async fn drain(conn_stop_tx: watch::Sender<()>, mut conns: JoinSet<()>, timeout: Duration) {
+    drop(conn_stop_tx);
+    let drained = tokio::time::timeout(timeout, async {
+        while conns.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!(
+            connections = conns.len(),
+            "drain timeout expired, aborting connections"
+        );
+        conns.abort_all();
+        while conns.join_next().await.is_some() {}
+    }
+}
+
+async fn compress_response(resp: Response, encoding: &'static str) -> Response {
+    if resp.status() == StatusCode::SWITCHING_PROTOCOLS
+        || resp.headers().contains_key(CONTENT_ENCODING)
+    {
+        return resp;
+    }
+    let (mut parts, body) = resp.into_parts();
+    let data = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(data) => data,
+        Err(err) => {
+            warn!(details = %err, "failed to read response body");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if data.is_empty() {
+        return Response::from_parts(parts, Body::from(data));
+    }
+    match encode_body(encoding, &data) {
+        Ok(data) => {
+            parts
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+            parts.headers.remove(CONTENT_LENGTH);
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("accept-encoding"));
+            Response::from_parts(parts, Body::from(data))
+        }
+        Err(err) => {
+            warn!(details = %err, encoding = %encoding, "failed to compress response body");
+            Response::from_parts(parts, Body::from(data))
+        }
+    }
+}
+
+fn decode_body(encoding: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+    encoding
+        .split(',')
+        .rev()
+        .try_fold(data.to_vec(), |data, encoding| {
+            let mut decoded = vec![];
+            match encoding.trim().to_lowercase().as_str() {
+                "br" => {
+                    brotli::Decompressor::new(data.as_slice(), 4096).read_to_end(&mut decoded)?;
+                }
+                "deflate" => {
+                    ZlibDecoder::new(data.as_slice()).read_to_end(&mut decoded)?;
+                }
+                "gzip" | "x-gzip" => {
+                    GzDecoder::new(data.as_slice()).read_to_end(&mut decoded)?;
+                }
+                "identity" => decoded = data,
+                encoding => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unsupported encoding `{encoding}`"),
+                    ));
+                }
+            }
+            Ok(decoded)
+        })
+}
+
+fn encode_body(encoding: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+    match encoding {
+        "br" => {
+            let mut encoder = brotli::CompressorWriter::new(vec![], 4096, 5, 22);
+            encoder.write_all(data)?;
+            Ok(encoder.into_inner())
+        }
+        _ => {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
+
+fn build_request(
+    method: &Method,
+    uri: &Uri,
+    query: Vec<(String, String)>,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> io::Result<HttpRequest> {
+    let mut req_headers = HashMap::<String, String>::new();
+    let mut raw_headers = vec![];
+    for (name, val) in headers {
+        raw_headers.push((name.as_str().into(), val.as_bytes().to_vec()));
+        if let Ok(val) = val.to_str() {
+            let sep = if name == COOKIE { "; " } else { ", " };
+            req_headers
+                .entry(name.as_str().into())
+                .and_modify(|vals| {
+                    vals.push_str(sep);
+                    vals.push_str(val);
+                })
+                .or_insert_with(|| val.into());
+        }
+    }
+    let query = query.into_iter().fold(
+        HashMap::<String, Vec<String>>::new(),
+        |mut query, (key, val)| {
+            query.entry(key).or_default().push(val);
+            query
+        },
+    );
+    let content_encoding = req_headers.get("content-encoding").cloned();
+    let body = match &content_encoding {
+        Some(encoding) => decode_body(encoding, body)?,
+        None => body.to_vec(),
+    };
+    Ok(HttpRequest {
+        body,
+        content_encoding,
+        headers: req_headers,
+        method: method.to_string(),
+        path: uri.path().into(),
+        path_params: HashMap::new(),
+        query,
+        raw_headers,
+    })
+}
+
+#[inline]
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(feature = "http-record")]
+async fn proxy(
+    client: &reqwest::Client,
+    upstream: &str,
+    req: &HttpRequest,
+    query: Option<&str>,
+) -> reqwest::Result<HttpRecordedResponse> {
+    let url = match query {
+        Some(query) => format!("{upstream}{}?{query}", req.path),
+        None => format!("{upstream}{}", req.path),
+    };
+    let method = reqwest::Method::from_bytes(req.method.as_bytes()).unwrap_or_default();
+    let mut builder = client.request(method, url).body(req.body.clone());
+    for (name, val) in &req.headers {
+        // The body is forwarded decoded.
+        if !matches!(
+            name.as_str(),
+            "content-encoding" | "content-length" | "host"
+        ) {
+            builder = builder.header(name, val);
+        }
+    }
+    let resp = builder.send().await?;
+    let status = resp.status().as_u16();
+    let headers = resp
+        .headers()
+        .iter()
+        .filter(|(name, _)| {
+            !matches!(
+                name.as_str(),
+                "connection" | "content-length" | "keep-alive" | "transfer-encoding"
+            )
+        })
+        .filter_map(|(name, val)| {
+            val.to_str()
+                .ok()
+                .map(|val| (name.as_str().into(), val.into()))
+        })
+        .collect();
+    let body = resp.bytes().await?.to_vec();
+    Ok(HttpRecordedResponse {
+        body,
+        headers,
+        status,
+    })
+}
+
+fn serve(
+    listener: TcpListener,
+    app: Router,
+    mut stop_rx: oneshot::Receiver<()>,
+    drain_timeout: Duration,
+) -> JoinHandle<()> {
+    spawn(async move {
+        let (conn_stop_tx, conn_stop_rx) = watch::channel(());
+        let mut conns = JoinSet::new();
+        loop {
+            let stream = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        warn!(details = %err, "failed to accept connection");
+                        continue;
+                    }
+                },
+                Some(_) = conns.join_next(), if !conns.is_empty() => continue,
+                _ = &mut stop_rx => break,
+            };
+            let slot = FaultSlot::default();
+            let app = app.clone().layer(Extension(slot.clone()));
+            let stream = FaultyStream {
+                inner: stream,
+                slot,
+            };
+            conns.spawn(serve_connection(stream, app, conn_stop_rx.clone()));
+        }
+        drain(conn_stop_tx, conns, drain_timeout).await;
+    })
+}
+
+#[cfg(feature = "http-tls")]
+fn serve_tls(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    app: Router,
+    mut stop_rx: oneshot::Receiver<()>,
+    drain_timeout: Duration,
+) -> JoinHandle<()> {
+    spawn(async move {
+        let (conn_stop_tx, conn_stop_rx) = watch::channel(());
+        let mut conns = JoinSet::new();
+        loop {
+            let stream = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        warn!(details = %err, "failed to accept connection");
+                        continue;
+                    }
+                },
+                Some(_) = conns.join_next(), if !conns.is_empty() => continue,
+                _ = &mut stop_rx => break,
+            };
+            let acceptor = acceptor.clone();
+            let app = app.clone();
+            let conn_stop_rx = conn_stop_rx.clone();
+            conns.spawn(async move {
+                match acceptor.accept(stream).await {
+                    Ok(stream) => serve_connection(stream, app, conn_stop_rx).await,
+                    Err(err) => warn!(details = %err, "failed to establish TLS connection"),
+                }
+            });
+        }
+        drain(conn_stop_tx, conns, drain_timeout).await;
+    })
+}
+
+#[cfg(unix)]
+fn serve_uds(
+    listener: UnixListener,
+    app: Router,
+    mut stop_rx: oneshot::Receiver<()>,
+    drain_timeout: Duration,
+) -> JoinHandle<()> {
+    spawn(async move {
+        let (conn_stop_tx, conn_stop_rx) = watch::channel(());
+        let mut conns = JoinSet::new();
+        loop {
+            let stream = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        warn!(details = %err, "failed to accept connection");
+                        continue;
+                    }
+                },
+                Some(_) = conns.join_next(), if !conns.is_empty() => continue,
+                _ = &mut stop_rx => break,
+            };
+            conns.spawn(serve_connection(stream, app.clone(), conn_stop_rx.clone()));
+        }
+        drain(conn_stop_tx, conns, drain_timeout).await;
+    })
+}
+
+async fn serve_connection<IO: AsyncRead + AsyncWrite + Send + Unpin + 'static>(
+    io: IO,
+    app: Router,
+    mut stop_rx: watch::Receiver<()>,
+) {
+    let builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+    let conn =
+        builder.serve_connection_with_upgrades(TokioIo::new(io), TowerToHyperService::new(app));
+    tokio::pin!(conn);
+    let res = tokio::select! {
+        res = conn.as_mut() => res,
+        _ = stop_rx.changed() => {
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    };
+    if let Err(err) = res {
+        warn!(details = %err, "failed to serve connection");
+    }
+}
+
+#[inline]
+fn har_content(body: &[u8]) -> Value {
+    match std::str::from_utf8(body) {
+        Ok(text) => json!({ "size": body.len(), "text": text }),
+        Err(_) => json!({ "encoding": "base64", "size": body.len(), "text": BASE64.encode(body) }),
+    }
+}
+
+#[inline]
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, val)| val.as_str())
+        .unwrap_or_default()
+}
+
+#[inline]
+fn insert_joined(headers: &mut HeaderMap, name: HeaderName, vals: &[String]) {
+    match HeaderValue::try_from(vals.join(", ")) {
+        Ok(val) => {
+            headers.insert(name, val);
+        }
+        Err(_) => warn!(header = %name, "invalid response header"),
+    }
+}
+
+#[inline]
+fn match_path(template: &str, path: &str) -> Option<HashMap<String, String>> {
+    let template: Vec<&str> = template.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    if template.len() != path.len() {
+        return None;
+    }
+    let mut params = HashMap::new();
+    for (template, segment) in template.into_iter().zip(path) {
+        match template.strip_prefix(':') {
+            Some(name) if !segment.is_empty() => {
+                params.insert(name.into(), segment.into());
+            }
+            _ if template == segment => {}
+            _ => return None,
+        }
+    }
+    Some(params)
+}
+
+#[inline]
+fn negotiate_encoding(accept: &str) -> Option<&'static str> {
+    let accepted: Vec<&str> = accept
+        .split(',')
+        .filter_map(|encoding| {
+            let mut params = encoding.split(';');
+            let name = params.next()?.trim();
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            (!refused).then_some(name)
+        })
+        .collect();
+    ["br", "gzip"].into_iter().find(|encoding| {
+        accepted
+            .iter()
+            .any(|accepted| accepted.eq_ignore_ascii_case(encoding))
+    })
+}
+
+#[cfg(feature = "http-record")]
+fn recorded_to_axum_response(recorded: HttpRecordedResponse) -> Response {
+    let mut resp = recorded.body.into_response();
+    *resp.status_mut() =
+        StatusCode::from_u16(recorded.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    resp.headers_mut().remove(CONTENT_TYPE);
+    for (name, val) in recorded.headers {
+        match (HeaderName::try_from(&name), HeaderValue::try_from(&val)) {
+            (Ok(name), Ok(val)) => {
+                resp.headers_mut().append(name, val);
+            }
+            _ => warn!(header = %name, "invalid response header"),
+        }
+    }
+    resp
+}
+
+fn to_axum_response(resp: HttpResponse) -> Response {
+    match resp {
+        HttpResponse::Empty => StatusCode::OK.into_response(),
+        HttpResponse::Html(html) => (StatusCode::OK, Html(html)).into_response(),
+        HttpResponse::Json(json) => (StatusCode::OK, Json(json)).into_response(),
+        HttpResponse::Status(status) => StatusCode::from_u16(status)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            .into_response(),
+        HttpResponse::Text(text) => (StatusCode::OK, text).into_response(),
+        HttpResponse::WithFault { resp, .. } => to_axum_response(*resp),
+        HttpResponse::WithHeaders { headers, resp } => {
+            let mut resp = to_axum_response(*resp);
+            for (name, val) in headers {
+                match (HeaderName::try_from(&name), HeaderValue::try_from(&val)) {
+                    (Ok(name), Ok(val)) => {
+                        resp.headers_mut().append(name, val);
+                    }
+                    _ => warn!(header = %name, "invalid response header"),
+                }
+            }
+            resp
+        }
+    }
+}
+
+fn parse_part(raw: &[u8]) -> Option<HttpPart> {
+    let sep = find_bytes(raw, b"\r\n\r\n")?;
+    let headers = std::str::from_utf8(&raw[..sep]).ok()?;
+    let mut content_type = None;
+    let mut filename = None;
+    let mut name = None;
+    for header in headers.split("\r\n").filter(|header| !header.is_empty()) {
+        let (key, val) = header.split_once(':')?;
+        let val = val.trim();
+        if key.trim().eq_ignore_ascii_case("content-disposition") {
+            for param in val.split(';').skip(1) {
+                if let Some((key, val)) = param.split_once('=') {
+                    let val = val.trim().trim_matches('"').to_string();
+                    match key.trim() {
+                        "filename" => filename = Some(val),
+                        "name" => name = Some(val),
+                        _ => {}
+                    }
+                }
+            }
+        } else if key.trim().eq_ignore_ascii_case("content-type") {
+            content_type = Some(val.to_string());
+        }
+    }
+    Some(HttpPart {
+        content_type,
+        data: raw[sep + 4..].to_vec(),
+        filename,
+        name: name?,
+    })
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use futures_util::{SinkExt, StreamExt};
+    use reqwest::{Client, Response};
+    use tokio_tungstenite::tungstenite;
+
+    use super::*;
+
+    // Consts
+
+    const ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    // Mods
+
+    mod default_http_server {
+        use super::*;
+
+        // run
+
+        async fn run(resp: HttpResponse) -> Response {
+            let mut server = DefaultHttpServer::with_response(&ADDR, resp)
+                .await
+                .expect("failed to start server");
+            let expected = HttpRequest {
+                body: "abc".to_string().into_bytes(),
+                content_encoding: None,
+                headers: HashMap::from_iter([
+                    ("accept".into(), "*/*".into()),
+                    ("content-length".into(), "3".into()),
+                    ("host".into(), server.local_addr().to_string()),
+                ]),
+                method: "GET".into(),
+                path: "/a/b".into(),
+                path_params: HashMap::new(),
+                query: HashMap::from_iter([("foo".into(), vec!["bar1".into(), "bar2".into()])]),
+                raw_headers: vec![
+                    ("accept".into(), b"*/*".to_vec()),
+                    ("content-length".into(), b"3".to_vec()),
+                    ("host".into(), server.local_addr().to_string().into_bytes()),
+                ],
+            };
+            let client = Client::new();
+            let query: Vec<(String, String)> = expected
+                .query
+                .clone()
+                .into_iter()
+                .flat_map(|(key, val)| val.into_iter().map(move |val| (key.clone(), val)))
+                .collect();
+            let resp = client
+                .get(format!("{}{}", server.url(), expected.path))
+                .query(&query)
+                .body(expected.body.clone())
+                .send()
+                .await
+                .expect("failed to send request");
+            let status = resp.status();
+            if status != reqwest::StatusCode::OK {
+                let body = resp.text().await.expect("failed to read response body");
+                panic!("request failed with status {status}: {body}");
+            }
+            let mut req = server.next().await.expect("failed to receive request");
+            req.raw_headers.sort();
+            assert_eq!(req, expected);
+            let url = server.url();
+            server.stop().await;
+            client
+                .get(url)
+                .send()
+                .await
+                .expect_err("request should fail after server is stopped");
+            resp
+        }
+
+        // Tests
+
+        #[tokio::test]
+        async fn auth() {
+            let mut server = DefaultHttpServer::builder()
+                .auth(HttpAuth::basic("user", "pass"))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let resp = client
+                .get(server.url())
+                .basic_auth("user", Some("wrong"))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                resp.headers().get("www-authenticate"),
+                Some(&reqwest::header::HeaderValue::from_static(
+                    "Basic realm=\"mockable\""
+                ))
+            );
+            let failure = server.next().await.expect("failed to receive request");
+            let resp = client
+                .get(server.url())
+                .basic_auth("user", Some("pass"))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            server.next().await.expect("failed to receive request");
+            assert_eq!(server.auth_failures(), vec![failure]);
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn compression() {
+            let mut server = DefaultHttpServer::builder()
+                .compression()
+                .fallback(HttpResponse::Text("hello".into()))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder
+                .write_all(b"abc")
+                .expect("failed to compress request body");
+            let body = encoder.finish().expect("failed to compress request body");
+            let resp = Client::new()
+                .post(server.url())
+                .header("accept-encoding", "gzip;q=1.0, br;q=0")
+                .header("content-encoding", "gzip")
+                .body(body)
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(
+                resp.headers().get("content-encoding"),
+                Some(&reqwest::header::HeaderValue::from_static("gzip"))
+            );
+            let body = resp.bytes().await.expect("failed to read response body");
+            let mut text = String::new();
+            GzDecoder::new(body.as_ref())
+                .read_to_string(&mut text)
+                .expect("failed to decompress response body");
+            assert_eq!(text, "hello");
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.body, b"abc");
+            assert_eq!(req.content_encoding, Some("gzip".into()));
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn cookies() {
+            let mut server = DefaultHttpServer::builder()
+                .fallback(
+                    HttpResponse::Empty
+                        .with_cookie(HttpCookie::new("session", "abc"))
+                        .with_cookie(HttpCookie::new("theme", "dark").with_path("/")),
+                )
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let resp = Client::new()
+                .get(server.url())
+                .send()
+                .await
+                .expect("failed to send request");
+            let cookies: Vec<_> = resp
+                .headers()
+                .get_all("set-cookie")
+                .iter()
+                .map(|val| val.to_str().expect("failed to decode header value"))
+                .collect();
+            assert_eq!(cookies, vec!["session=abc", "theme=dark; Path=/"]);
+            server.next().await.expect("failed to receive request");
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn cors() {
+            let mut server = DefaultHttpServer::builder()
+                .cors(
+                    HttpCors::new()
+                        .with_origin("http://example.com")
+                        .with_method("GET")
+                        .with_method("PUT")
+                        .with_max_age(Duration::from_secs(600)),
+                )
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let resp = client
+                .request(reqwest::Method::OPTIONS, format!("{}/a", server.url()))
+                .header("origin", "http://example.com")
+                .header("access-control-request-method", "PUT")
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::NO_CONTENT);
+            let header = |resp: &Response, name: &str| {
+                resp.headers().get(name).map(|val| {
+                    val.to_str()
+                        .expect("failed to decode header value")
+                        .to_string()
+                })
+            };
+            assert_eq!(
+                header(&resp, "access-control-allow-origin"),
+                Some("http://example.com".into())
+            );
+            assert_eq!(
+                header(&resp, "access-control-allow-methods"),
+                Some("GET, PUT".into())
+            );
+            assert_eq!(header(&resp, "access-control-max-age"), Some("600".into()));
+            let resp = client
+                .get(format!("{}/b", server.url()))
+                .header("origin", "http://example.com")
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(
+                header(&resp, "access-control-allow-origin"),
+                Some("http://example.com".into())
+            );
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/b");
+            let resp = client
+                .get(format!("{}/c", server.url()))
+                .header("origin", "http://other.com")
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(header(&resp, "access-control-allow-origin"), None);
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/c");
+            let summary = server.shutdown().await;
+            assert_eq!(summary.dropped, 0);
+            assert_eq!(summary.served, 3);
+        }
+
+        #[tokio::test]
+        async fn drain_timeout() {
+            let server = DefaultHttpServer::builder()
+                .drain_timeout(Duration::from_millis(100))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let resp = client
+                .get(server.url())
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            let blocked = spawn({
+                let client = client.clone();
+                let url = server.url();
+                async move { client.get(url).send().await }
+            });
+            while server.state.requests.load(Ordering::Relaxed) < 2 {
+                tokio::task::yield_now().await;
+            }
+            let summary = server.shutdown().await;
+            assert_eq!(
+                summary,
+                HttpStopSummary {
+                    dropped: 1,
+                    served: 1,
+                }
+            );
+            blocked
+                .await
+                .expect("failed to join task")
+                .expect_err("request should fail");
+        }
+
+        #[tokio::test]
+        async fn drop_overflow_policy() {
+            let mut server = DefaultHttpServer::builder()
+                .channel_capacity(2)
+                .overflow_policy(HttpOverflowPolicy::Drop)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            for path in ["/a", "/b", "/c"] {
+                let resp = client
+                    .get(format!("{}{path}", server.url()))
+                    .send()
+                    .await
+                    .expect("failed to send request");
+                assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            }
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/a");
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/b");
+            let url = server.url();
+            server.stop().await;
+            client
+                .get(url)
+                .send()
+                .await
+                .expect_err("request should fail after server is stopped");
+        }
+
+        #[tokio::test]
+        async fn empty() {
+            let resp = run(HttpResponse::Empty).await;
+            let text = resp.text().await.expect("failed to read response body");
+            assert!(text.is_empty());
+        }
+
+        #[tokio::test]
+        async fn expectations() {
+            let mut server = DefaultHttpServer::builder()
+                .expect(
+                    HttpExpectation::new(
+                        HttpRequestMatcher::new("POST", "/token")
+                            .with_header("Content-Type", "text/plain")
+                            .with_body_matching(|body| body == b"secret"),
+                        HttpResponse::Text("token".into()),
+                    )
+                    .with_times(1),
+                )
+                .fallback(HttpResponse::Status(404))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let resp = client
+                .post(format!("{}/token", server.url()))
+                .header("content-type", "text/plain")
+                .body("secret")
+                .send()
+                .await
+                .expect("failed to send request");
+            let text = resp.text().await.expect("failed to read response body");
+            assert_eq!(text, "token");
+            server.next().await.expect("failed to receive request");
+            server.verify();
+            let resp = client
+                .get(format!("{}/unknown", server.url()))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+            server.next().await.expect("failed to receive request");
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| server.verify()));
+            assert!(res.is_err());
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn faults() {
+            let body = "hello world";
+            let mut server = DefaultHttpServer::builder()
+                .route(
+                    "GET",
+                    "/close",
+                    HttpResponse::Text(body.into()).with_fault(HttpFault::CloseWithoutResponse),
+                )
+                .route(
+                    "GET",
+                    "/malformed",
+                    HttpResponse::Text(body.into()).with_fault(HttpFault::MalformedStatusLine),
+                )
+                .route(
+                    "GET",
+                    "/reset",
+                    HttpResponse::Text(body.into()).with_fault(HttpFault::ResetAfter(10)),
+                )
+                .route(
+                    "GET",
+                    "/truncated",
+                    HttpResponse::Text(body.into()).with_fault(HttpFault::TruncatedBody(5)),
+                )
+                .fallback(HttpResponse::Text(body.into()))
+                .fault_on_request(5, HttpFault::CloseWithoutResponse)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::builder()
+                .pool_max_idle_per_host(0)
+                .build()
+                .expect("failed to build client");
+            for path in ["/close", "/malformed", "/reset"] {
+                client
+                    .get(format!("{}{path}", server.url()))
+                    .send()
+                    .await
+                    .expect_err("request should fail");
+                server.next().await.expect("failed to receive request");
+            }
+            let resp = client
+                .get(format!("{}/truncated", server.url()))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            resp.text().await.expect_err("body should be truncated");
+            server.next().await.expect("failed to receive request");
+            client
+                .get(server.url())
+                .send()
+                .await
+                .expect_err("request should fail");
+            server.next().await.expect("failed to receive request");
+            let text = client
+                .get(server.url())
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, body);
+            server.next().await.expect("failed to receive request");
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn grow_overflow_policy() {
+            let mut server = DefaultHttpServer::builder()
+                .overflow_policy(HttpOverflowPolicy::Grow)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            for path in ["/a", "/b", "/c"] {
+                client
+                    .get(format!("{}{path}", server.url()))
+                    .send()
+                    .await
+                    .expect("failed to send request");
+            }
+            for path in ["/a", "/b", "/c"] {
+                let req = server.next().await.expect("failed to receive request");
+                assert_eq!(req.path, path);
+            }
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn h2c() {
+            let mut server =
+                DefaultHttpServer::with_response(&ADDR, HttpResponse::Text("ok".into()))
+                    .await
+                    .expect("failed to start server");
+            let resp = Client::builder()
+                .http2_prior_knowledge()
+                .build()
+                .expect("failed to build client")
+                .get(server.url())
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.version(), reqwest::Version::HTTP_2);
+            let text = resp.text().await.expect("failed to read response body");
+            assert_eq!(text, "ok");
+            server.next().await.expect("failed to receive request");
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn har() {
+            let mut server = DefaultHttpServer::builder()
+                .har(10)
+                .route("POST", "/a", HttpResponse::Json(json!({ "foo": "bar" })))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            client
+                .post(format!("{}/a?q=1", server.url()))
+                .body("abc")
+                .send()
+                .await
+                .expect("failed to send request");
+            server.next().await.expect("failed to receive request");
+            let har = server.export_har();
+            let entries = har["log"]["entries"]
+                .as_array()
+                .expect("entries should be an array");
+            assert_eq!(entries.len(), 1);
+            let entry = &entries[0];
+            assert_eq!(entry["request"]["method"], "POST");
+            assert_eq!(entry["request"]["url"], format!("{}/a?q=1", server.url()));
+            assert_eq!(
+                entry["request"]["queryString"],
+                json!([{ "name": "q", "value": "1" }])
+            );
+            assert_eq!(entry["request"]["postData"]["text"], "abc");
+            assert_eq!(entry["response"]["status"], 200);
+            assert_eq!(entry["response"]["content"]["text"], r#"{"foo":"bar"}"#);
+            assert_eq!(entry["response"]["content"]["mimeType"], "application/json");
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn har_capacity() {
+            let mut server = DefaultHttpServer::builder()
+                .har(1)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            for path in ["a", "b"] {
+                client
+                    .get(format!("{}/{path}", server.url()))
+                    .send()
+                    .await
+                    .expect("failed to send request");
+                server.next().await.expect("failed to receive request");
+            }
+            let har = server.export_har();
+            let entries = har["log"]["entries"]
+                .as_array()
+                .expect("entries should be an array");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0]["request"]["url"], format!("{}/b", server.url()));
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        #[should_panic(expected = "HAR capture is not enabled")]
+        async fn har_disabled() {
+            let server = DefaultHttpServer::builder()
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            server.export_har();
+        }
+
+        #[tokio::test]
+        async fn har_streaming() {
+            let router = Router::new().route(
+                "/events",
+                axum::routing::get(|| async {
+                    let stream = futures_util::stream::once(async {
+                        Ok::<_, io::Error>(Bytes::from("data: hello\n\n"))
+                    })
+                    .chain(futures_util::stream::pending());
+                    Body::from_stream(stream)
+                }),
+            );
+            let mut server = DefaultHttpServer::builder()
+                .har(10)
+                .router(router)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let mut resp = Client::new()
+                .get(format!("{}/events", server.url()))
+                .send()
+                .await
+                .expect("failed to send request");
+            let chunk = tokio::time::timeout(Duration::from_secs(5), resp.chunk())
+                .await
+                .expect("response should be streamed")
+                .expect("failed to read response body");
+            assert_eq!(chunk.as_deref(), Some(b"data: hello\n\n".as_ref()));
+            drop(resp);
+            server.next().await.expect("failed to receive request");
+            let entries = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let har = server.export_har();
+                    let entries = har["log"]["entries"].clone();
+                    if entries
+                        .as_array()
+                        .is_some_and(|entries| !entries.is_empty())
+                    {
+                        break entries;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("exchange should be captured");
+            assert_eq!(entries[0]["response"]["content"]["text"], "data: hello\n\n");
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn headers() {
+            let mut server = DefaultHttpServer::builder()
+                .expect(HttpExpectation::new(
+                    HttpRequestMatcher::new("GET", "/").with_header("Via", "proxy2"),
+                    HttpResponse::Empty,
+                ))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let resp = Client::new()
+                .get(server.url())
+                .header("via", "proxy1")
+                .header("via", "proxy2")
+                .header(
+                    "x-binary",
+                    reqwest::header::HeaderValue::from_bytes(b"\xff\xfe")
+                        .expect("failed to create header value"),
+                )
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.header("Via"), Some("proxy1, proxy2"));
+            assert_eq!(req.headers_all("VIA"), vec!["proxy1", "proxy2"]);
+            assert_eq!(req.header("x-binary"), None);
+            assert!(req
+                .raw_headers
+                .contains(&("x-binary".into(), b"\xff\xfe".to_vec())));
+            server.verify();
+            server.stop().await;
+        }
+
+        #[tokio::test]
+        async fn html() {
+            let expected = "<head></head>";
+            let resp = run(HttpResponse::Html(expected.into())).await;
+            let text = resp.text().await.expect("failed to read response body");
+            assert_eq!(text, expected);
+        }
+
+        #[tokio::test]
+        async fn json() {
+            let expected = Value::String("val".into());
+            let resp = run(HttpResponse::Json(expected.clone())).await;
+            let json: Value = resp.json().await.expect("failed to read response body");
+            assert_eq!(json, expected);
+        }
+
+        #[tokio::test]
+        async fn path_templates() {
+            let mut server = DefaultHttpServer::builder()
+                .route(
+                    "GET",
+                    "/users/:id/posts/:post_id",
+                    HttpResponse::Text("post".into()),
+                )
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let text = client
+                .get(format!("{}/users/1/posts/2", server.url()))
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "post");
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(
+                req.path_params,
+                HashMap::from_iter([("id".into(), "1".into()), ("post_id".into(), "2".into())])
+            );
+            let text = client
+                .get(format!("{}/users/1/posts", server.url()))
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "");
+            let req = server.next().await.expect("failed to receive request");
+            assert!(req.path_params.is_empty());
+            server.stop().await;
+        }
+
+        #[cfg(feature = "http-record")]
+        #[tokio::test]
+        async fn record_replay() {
+            let cassette =
+                std::env::temp_dir().join(format!("mockable-cassette-{}.json", std::process::id()));
+            let mut upstream = DefaultHttpServer::builder()
+                .route("GET", "/hello", HttpResponse::Text("hello".into()))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let mut server = DefaultHttpServer::builder()
+                .record(upstream.url(), &cassette)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let text = client
+                .get(format!("{}/hello?foo=bar", server.url()))
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "hello");
+            upstream.next().await.expect("failed to receive request");
+            server.next().await.expect("failed to receive request");
+            upstream.stop().await;
+            server.stop().await;
+            let mut server = DefaultHttpServer::builder()
+                .replay(&cassette, HttpReplayRules::default())
+                .fallback(HttpResponse::Status(404))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let resp = client
+                .get(format!("{}/hello?foo=bar", server.url()))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(
+                resp.headers()
+                    .get("content-type")
+                    .and_then(|val| val.to_str().ok()),
+                Some("text/plain; charset=utf-8")
+            );
+            let text = resp.text().await.expect("failed to read response body");
+            assert_eq!(text, "hello");
+            server.next().await.expect("failed to receive request");
+            let resp = client
+                .get(format!("{}/hello", server.url()))
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+            server.next().await.expect("failed to receive request");
+            server.stop().await;
+            fs::remove_file(&cassette).ok();
+        }
+
+        #[cfg(feature = "http-record")]
+        #[tokio::test]
+        async fn record_compressed() {
+            let cassette = std::env::temp_dir().join(format!(
+                "mockable-cassette-compressed-{}.json",
+                std::process::id()
+            ));
+            let mut upstream = DefaultHttpServer::builder()
+                .route("POST", "/hello", HttpResponse::Text("hello".into()))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let mut server = DefaultHttpServer::builder()
+                .record(upstream.url(), &cassette)
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder
+                .write_all(b"abc")
+                .expect("failed to compress request body");
+            let body = encoder.finish().expect("failed to compress request body");
+            let text = Client::new()
+                .post(format!("{}/hello", server.url()))
+                .header("content-encoding", "gzip")
+                .body(body)
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "hello");
+            let req = upstream.next().await.expect("failed to receive request");
+            assert_eq!(req.body, b"abc");
+            assert_eq!(req.content_encoding, None);
+            assert!(!req.headers.contains_key("content-encoding"));
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.body, b"abc");
+            assert_eq!(req.content_encoding, Some("gzip".into()));
+            upstream.stop().await;
+            server.stop().await;
+            let cassette_content = HttpCassette::load(&cassette).expect("failed to load cassette");
+            assert_eq!(cassette_content.interactions[0].resp.status, 200);
+            assert_eq!(cassette_content.interactions[0].resp.body, b"hello");
+            fs::remove_file(&cassette).ok();
+        }
+
+        #[tokio::test]
+        async fn router() {
+            let router = Router::new().route(
+                "/users/:id",
+                axum::routing::get(
+                    |axum::extract::Path(id): axum::extract::Path<String>| async move {
+                        format!("user {id}")
+                    },
+                ),
+            );
+            let mut server = DefaultHttpServer::with_router(&ADDR, router)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let text = client
+                .get(format!("{}/users/1", server.url()))
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "user 1");
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/users/1");
+            let resp = client
+                .post(format!("{}/unknown?foo=bar", server.url()))
+                .body("abc")
+                .send()
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.body, b"abc");
+            assert_eq!(
+                req.query,
+                HashMap::from_iter([("foo".into(), vec!["bar".into()])])
+            );
+            let summary = server.shutdown().await;
+            assert_eq!(summary.dropped, 0);
+            assert_eq!(summary.served, 2);
+        }
+
+        #[tokio::test]
+        async fn routes() {
+            let mut server = DefaultHttpServer::builder()
+                .route("get", "/health", HttpResponse::Text("ok".into()))
+                .route("POST", "/token", HttpResponse::Json(Value::Bool(true)))
+                .fallback(HttpResponse::Text("fallback".into()))
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let client = Client::new();
+            let cases = [
+                (reqwest::Method::GET, "/health", "ok"),
+                (reqwest::Method::POST, "/token", "true"),
+                (reqwest::Method::POST, "/health", "fallback"),
+            ];
+            for (method, path, expected) in cases {
+                let resp = client
+                    .request(method.clone(), format!("{}{path}", server.url()))
+                    .send()
+                    .await
+                    .expect("failed to send request");
+                let text = resp.text().await.expect("failed to read response body");
+                assert_eq!(text, expected);
+                let req = server.next().await.expect("failed to receive request");
+                assert_eq!(req.method, method.as_str());
+                assert_eq!(req.path, path);
+            }
+            server.stop().await;
         }
 
         #[tokio::test]
         async fn text() {
             let expected = "val";
-            let resp = run(8003, HttpResponse::Text(expected.into())).await;
+            let resp = run(HttpResponse::Text(expected.into())).await;
             let text = resp.text().await.expect("failed to read response body");
             assert_eq!(text, expected);
         }
+
+        #[cfg(feature = "http-tls")]
+        #[tokio::test]
+        async fn tls() {
+            let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()])
+                .expect("failed to generate certificate");
+            let mut server = DefaultHttpServer::builder()
+                .fallback(HttpResponse::Text("ok".into()))
+                .tls(
+                    cert.serialize_pem()
+                        .expect("failed to serialize certificate"),
+                    cert.serialize_private_key_pem(),
+                )
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            assert!(server.url().starts_with("https://"));
+            let text = Client::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .expect("failed to build client")
+                .get(server.url())
+                .send()
+                .await
+                .expect("failed to send request")
+                .text()
+                .await
+                .expect("failed to read response body");
+            assert_eq!(text, "ok");
+            server.next().await.expect("failed to receive request");
+            server.stop().await;
+        }
+
+        #[cfg(unix)]
+        #[tokio::test]
+        async fn uds() {
+            use tokio::{
+                io::{AsyncReadExt, AsyncWriteExt},
+                net::UnixStream,
+            };
+
+            let path = std::env::temp_dir().join(format!("mockable-{}.sock", std::process::id()));
+            let mut server = DefaultHttpServer::builder()
+                .fallback(HttpResponse::Text("ok".into()))
+                .start_uds(&path)
+                .await
+                .expect("failed to start server");
+            assert_eq!(server.socket_path(), Some(path.as_path()));
+            let mut stream = UnixStream::connect(&path).await.expect("failed to connect");
+            stream
+                .write_all(b"GET /a HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .expect("failed to send request");
+            let mut resp = String::new();
+            stream
+                .read_to_string(&mut resp)
+                .await
+                .expect("failed to read response");
+            assert!(resp.starts_with("HTTP/1.1 200 OK"));
+            assert!(resp.ends_with("ok"));
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.path, "/a");
+            server.stop().await;
+            assert!(!path.exists());
+        }
+
+        #[tokio::test]
+        async fn websocket() {
+            let mut server = DefaultHttpServer::builder()
+                .websocket("/ws")
+                .start(&ADDR)
+                .await
+                .expect("failed to start server");
+            let url = format!("ws://{}/ws", server.local_addr());
+            let (mut client, _) = tokio_tungstenite::connect_async(url)
+                .await
+                .expect("failed to connect");
+            client
+                .send(tungstenite::Message::Text("ping".into()))
+                .await
+                .expect("failed to send message");
+            let msg = server
+                .next_message()
+                .await
+                .expect("failed to receive message");
+            assert_eq!(msg, WebSocketMessage::Text("ping".into()));
+            assert_eq!(server.send(WebSocketMessage::Text("pong".into())), 1);
+            let msg = client
+                .next()
+                .await
+                .expect("connection closed")
+                .expect("failed to receive message");
+            assert_eq!(msg, tungstenite::Message::Text("pong".into()));
+            server.send(WebSocketMessage::Close {
+                code: 1000,
+                reason: "bye".into(),
+            });
+            let msg = client
+                .next()
+                .await
+                .expect("connection closed")
+                .expect("failed to receive message");
+            assert!(matches!(msg, tungstenite::Message::Close(_)));
+            server.stop().await;
+        }
+    }
+
+    mod http_auth {
+        use super::*;
+
+        // run
+
+        fn run(auth: &HttpAuth, header: Option<&str>) -> bool {
+            let req = match header {
+                Some(header) => HttpRequest::new("GET", "/").with_header("Authorization", header),
+                None => HttpRequest::new("GET", "/"),
+            };
+            auth.verify(&req)
+        }
+
+        // Tests
+
+        #[test]
+        fn basic() {
+            let auth = HttpAuth::basic("user", "pass");
+            let valid = format!("Basic {}", BASE64.encode("user:pass"));
+            let invalid = format!("Basic {}", BASE64.encode("user:wrong"));
+            assert!(run(&auth, Some(&valid)));
+            assert!(!run(&auth, Some(&invalid)));
+            assert!(!run(&auth, Some("Bearer pass")));
+            assert!(!run(&auth, None));
+        }
+
+        #[test]
+        fn bearer() {
+            let auth = HttpAuth::Bearer("token".into());
+            assert!(run(&auth, Some("Bearer token")));
+            assert!(run(&auth, Some("bearer token")));
+            assert!(!run(&auth, Some("Bearer other")));
+            assert!(!run(&auth, None));
+        }
+
+        #[cfg(feature = "http-jwt")]
+        #[test]
+        fn jwt() {
+            let auth = HttpAuth::Jwt("secret".into());
+            let encode = |secret: &str, exp: i64| {
+                jsonwebtoken::encode(
+                    &jsonwebtoken::Header::default(),
+                    &serde_json::json!({ "exp": exp, "sub": "user" }),
+                    &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
+                )
+                .expect("failed to encode token")
+            };
+            let exp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("failed to get current time")
+                .as_secs() as i64
+                + 3600;
+            assert!(run(
+                &auth,
+                Some(&format!("Bearer {}", encode("secret", exp)))
+            ));
+            assert!(!run(
+                &auth,
+                Some(&format!("Bearer {}", encode("other", exp)))
+            ));
+            assert!(!run(
+                &auth,
+                Some(&format!("Bearer {}", encode("secret", exp - 7200)))
+            ));
+        }
+    }
+
+    mod http_cookie {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn display() {
+            let cookie = HttpCookie::new("session", "abc")
+                .with_domain("example.com")
+                .with_http_only()
+                .with_max_age(Duration::from_secs(3600))
+                .with_path("/")
+                .with_same_site(HttpSameSite::Strict)
+                .with_secure();
+            assert_eq!(
+                cookie.to_string(),
+                "session=abc; Domain=example.com; Max-Age=3600; Path=/; SameSite=Strict; HttpOnly; Secure"
+            );
+        }
+    }
+
+    mod http_request {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn new() {
+            let req = HttpRequest::new("get", "/users")
+                .with_body("abc")
+                .with_content_encoding("gzip")
+                .with_header("Accept", "*/*")
+                .with_path_param("id", "1")
+                .with_query_param("foo", "bar1")
+                .with_query_param("foo", "bar2")
+                .with_raw_header("Accept", "*/*");
+            let expected = HttpRequest {
+                body: b"abc".to_vec(),
+                content_encoding: Some("gzip".into()),
+                headers: HashMap::from_iter([("accept".into(), "*/*".into())]),
+                method: "GET".into(),
+                path: "/users".into(),
+                path_params: HashMap::from_iter([("id".into(), "1".into())]),
+                query: HashMap::from_iter([("foo".into(), vec!["bar1".into(), "bar2".into()])]),
+                raw_headers: vec![("accept".into(), b"*/*".to_vec())],
+            };
+            assert_eq!(req, expected);
+        }
+
+        #[test]
+        fn cookies() {
+            let req = HttpRequest::new("GET", "/").with_header("Cookie", "session=abc; theme=dark");
+            let expected = HashMap::from_iter([
+                ("session".into(), "abc".into()),
+                ("theme".into(), "dark".into()),
+            ]);
+            assert_eq!(req.cookies(), expected);
+        }
+
+        #[test]
+        fn body() {
+            let req = HttpRequest::new("POST", "/").with_body(r#"{"foo":"bar"}"#);
+            assert_eq!(
+                req.body_string().expect("failed to decode body"),
+                r#"{"foo":"bar"}"#
+            );
+            let json: HashMap<String, String> = req.body_json().expect("failed to decode body");
+            assert_eq!(json, HashMap::from_iter([("foo".into(), "bar".into())]));
+        }
+
+        #[test]
+        fn form() {
+            let req = HttpRequest::new("POST", "/").with_body("foo=bar1&foo=bar2&name=John+Doe");
+            let expected = HashMap::from_iter([
+                ("foo".into(), vec!["bar1".into(), "bar2".into()]),
+                ("name".into(), vec!["John Doe".into()]),
+            ]);
+            assert_eq!(req.form(), expected);
+        }
+
+        #[test]
+        fn multipart() {
+            let body = "--XyZ\r\n\
+                Content-Disposition: form-data; name=\"title\"\r\n\
+                \r\n\
+                hello\r\n\
+                --XyZ\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                Content-Type: text/plain\r\n\
+                \r\n\
+                line1\r\nline2\r\n\
+                --XyZ--\r\n";
+            let req = HttpRequest::new("POST", "/")
+                .with_body(body)
+                .with_header("Content-Type", "multipart/form-data; boundary=XyZ");
+            let expected = vec![
+                HttpPart {
+                    content_type: None,
+                    data: b"hello".to_vec(),
+                    filename: None,
+                    name: "title".into(),
+                },
+                HttpPart {
+                    content_type: Some("text/plain".into()),
+                    data: b"line1\r\nline2".to_vec(),
+                    filename: Some("a.txt".into()),
+                    name: "file".into(),
+                },
+            ];
+            assert_eq!(req.multipart(), Some(expected));
+        }
+
+        #[test]
+        fn multipart_not_multipart() {
+            let req = HttpRequest::new("POST", "/").with_header("Content-Type", "text/plain");
+            assert!(req.multipart().is_none());
+        }
     }
 }
@@ -0,0 +1,759 @@
+#[cfg(any(feature = "mock", feature = "sleep"))]
+use std::sync::Arc;
+#[cfg(feature = "mock")]
+use std::sync::{Mutex, PoisonError};
+use std::{io, str::Utf8Error, time::Duration};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "sleep")]
+use tracing::warn;
+
+#[cfg(feature = "sleep")]
+use crate::Sleeper;
+
+#[cfg(feature = "sleep")]
+type HttpErrorRetryPredicate = dyn Fn(&io::Error) -> bool + Send + Sync;
+#[cfg(feature = "sleep")]
+type HttpStatusRetryPredicate = dyn Fn(u16) -> bool + Send + Sync;
+
+// HttpClientRequest
+
+/// Request sent by an [`HttpClient`](trait.HttpClient.html).
+///
+/// **This is supported on `feature=http-client` only.**
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpClientRequest {
+    pub body: Vec<u8>,
+    pub headers: Vec<(String, String)>,
+    pub method: String,
+    pub timeout: Option<Duration>,
+    pub url: String,
+}
+
+impl HttpClientRequest {
+    /// Creates a new request with the given method (case insensitive) and URL.
+    pub fn new<M: Into<String>, U: Into<String>>(method: M, url: U) -> Self {
+        Self {
+            body: vec![],
+            headers: vec![],
+            method: method.into().to_uppercase(),
+            timeout: None,
+            url: url.into(),
+        }
+    }
+
+    /// Creates a new `DELETE` request.
+    pub fn delete<U: Into<String>>(url: U) -> Self {
+        Self::new("DELETE", url)
+    }
+
+    /// Creates a new `GET` request.
+    pub fn get<U: Into<String>>(url: U) -> Self {
+        Self::new("GET", url)
+    }
+
+    /// Creates a new `POST` request.
+    pub fn post<U: Into<String>>(url: U) -> Self {
+        Self::new("POST", url)
+    }
+
+    /// Creates a new `PUT` request.
+    pub fn put<U: Into<String>>(url: U) -> Self {
+        Self::new("PUT", url)
+    }
+
+    /// Sets the body.
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Adds a header.
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.headers.push((name.into(), val.into()));
+        self
+    }
+
+    /// Sets the body to `body` serialized as JSON and the `Content-Type` header to
+    /// `application/json`.
+    pub fn with_json<T: Serialize>(self, body: &T) -> serde_json::Result<Self> {
+        let body = serde_json::to_vec(body)?;
+        Ok(self
+            .with_header("content-type", "application/json")
+            .with_body(body))
+    }
+
+    /// Sets the timeout of the request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+// HttpClientResponse
+
+/// Response received by an [`HttpClient`](trait.HttpClient.html).
+///
+/// **This is supported on `feature=http-client` only.**
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpClientResponse {
+    pub body: Vec<u8>,
+    pub headers: Vec<(String, String)>,
+    pub status: u16,
+}
+
+impl HttpClientResponse {
+    /// Creates a new response with the given status code and an empty body.
+    pub fn new(status: u16) -> Self {
+        Self {
+            body: vec![],
+            headers: vec![],
+            status,
+        }
+    }
+
+    /// Returns the first value of the header `name` (case insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
+    }
+
+    /// Returns `true` if the status code is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the body as JSON.
+    ///
+    /// If it is not valid JSON or doesn't match `T`, an error is returned.
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+
+    /// Returns the body as text.
+    ///
+    /// If it is not valid UTF-8, an error is returned.
+    pub fn text(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.body)
+    }
+
+    /// Sets the body.
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Adds a header.
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, val: V) -> Self {
+        self.headers.push((name.into(), val.into()));
+        self
+    }
+}
+
+// HttpClient
+
+/// A trait for sending HTTP requests.
+///
+/// **This is supported on `feature=http-client` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http_client.rs).
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    /// Sends the request and returns its response.
+    ///
+    /// Responses with an error status code are returned as is. An error is returned only if the
+    /// response can't be received.
+    async fn send(&self, req: HttpClientRequest) -> io::Result<HttpClientResponse>;
+}
+
+// DefaultHttpClient
+
+/// Default implementation of [`HttpClient`](trait.HttpClient.html) based on `reqwest`.
+///
+/// **This is supported on `feature=http-client` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http_client.rs).
+#[derive(Clone, Debug, Default)]
+pub struct DefaultHttpClient(reqwest::Client);
+
+impl DefaultHttpClient {
+    /// Creates a new client.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new client based on the given `reqwest` client.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self(client)
+    }
+}
+
+#[async_trait]
+impl HttpClient for DefaultHttpClient {
+    async fn send(&self, req: HttpClientRequest) -> io::Result<HttpClientResponse> {
+        let method = reqwest::Method::from_bytes(req.method.as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let mut builder = self.0.request(method, &req.url).body(req.body);
+        for (name, val) in req.headers {
+            builder = builder.header(name, val);
+        }
+        if let Some(timeout) = req.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let resp = builder.send().await.map_err(reqwest_to_io_error)?;
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter_map(|(name, val)| {
+                val.to_str()
+                    .ok()
+                    .map(|val| (name.as_str().into(), val.into()))
+            })
+            .collect();
+        let body = resp.bytes().await.map_err(reqwest_to_io_error)?.to_vec();
+        Ok(HttpClientResponse {
+            body,
+            headers,
+            status,
+        })
+    }
+}
+
+// FakeHttpClient
+
+/// Implementation of [`HttpClient`](trait.HttpClient.html) that returns canned responses and
+/// records the requests it receives.
+///
+/// The response to a request is the one of the first mapping whose method (case insensitive) and
+/// URL pattern match it. In a pattern, `*` matches any sequence of characters (like
+/// `https://api.example.com/users/*`). By default, an error is returned if no mapping matches.
+///
+/// Clones share the same recorded requests.
+///
+/// **This is supported on `feature=http-client,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http_client.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone, Default)]
+pub struct FakeHttpClient {
+    panic_on_unexpected: bool,
+    reqs: Arc<Mutex<Vec<HttpClientRequest>>>,
+    resps: Vec<(String, String, HttpClientResponse)>,
+}
+
+#[cfg(feature = "mock")]
+impl FakeHttpClient {
+    /// Creates a new client that does not expect any request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the requests received so far, in order.
+    pub fn requests(&self) -> Vec<HttpClientRequest> {
+        self.reqs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Panic instead of returning an error if a request is unexpected.
+    pub fn with_panic_on_unexpected(mut self) -> Self {
+        self.panic_on_unexpected = true;
+        self
+    }
+
+    /// Adds the response to return when a request matches `method` and `url`.
+    pub fn with_response<M: Into<String>, U: Into<String>>(
+        mut self,
+        method: M,
+        url: U,
+        resp: HttpClientResponse,
+    ) -> Self {
+        self.resps
+            .push((method.into().to_uppercase(), url.into(), resp));
+        self
+    }
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl HttpClient for FakeHttpClient {
+    async fn send(&self, req: HttpClientRequest) -> io::Result<HttpClientResponse> {
+        let resp = self
+            .resps
+            .iter()
+            .find(|(method, url, _)| {
+                req.method.eq_ignore_ascii_case(method) && matches_pattern(url, &req.url)
+            })
+            .map(|(_, _, resp)| resp.clone());
+        let msg = format!("unexpected request: {} {}", req.method, req.url);
+        self.reqs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(req);
+        match resp {
+            Some(resp) => Ok(resp),
+            None if self.panic_on_unexpected => panic!("{msg}"),
+            None => Err(io::Error::other(msg)),
+        }
+    }
+}
+
+// MockHttpClient
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`HttpClient`](trait.HttpClient.html).
+    ///
+    /// **This is supported on `feature=http-client,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http_client.rs).
+    pub HttpClient {}
+
+    #[async_trait]
+    impl HttpClient for HttpClient {
+        async fn send(&self, req: HttpClientRequest) -> io::Result<HttpClientResponse>;
+    }
+}
+
+// HttpRetryPolicy
+
+/// Policy describing when and how a request should be retried.
+///
+/// **This is supported on `feature=http-client,sleep` only.**
+#[cfg(feature = "sleep")]
+#[derive(Clone)]
+pub struct HttpRetryPolicy {
+    /// The delay before the first retry.
+    pub backoff: Duration,
+    /// The maximum total delay spent waiting between the attempts of a request.
+    ///
+    /// If waiting before the next attempt would exceed it, the last result is returned.
+    pub budget: Option<Duration>,
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+    /// The factor by which the delay is multiplied after each retry.
+    pub multiplier: u32,
+    /// The predicate that returns `true` if an IO error should be retried.
+    pub retry_on_error: Arc<HttpErrorRetryPredicate>,
+    /// The predicate that returns `true` if a status code should be retried.
+    pub retry_on_status: Arc<HttpStatusRetryPredicate>,
+}
+
+#[cfg(feature = "sleep")]
+impl HttpRetryPolicy {
+    /// Set the delay before the first retry.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the maximum total delay spent waiting between the attempts of a request.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Set the maximum number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the maximum delay between two attempts.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set the backoff multiplier.
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set the predicate that returns `true` if an IO error should be retried.
+    pub fn with_retry_on_error<F: Fn(&io::Error) -> bool + Send + Sync + 'static>(
+        mut self,
+        retry_on_error: F,
+    ) -> Self {
+        self.retry_on_error = Arc::new(retry_on_error);
+        self
+    }
+
+    /// Set the predicate that returns `true` if a status code should be retried.
+    pub fn with_retry_on_status<F: Fn(u16) -> bool + Send + Sync + 'static>(
+        mut self,
+        retry_on_status: F,
+    ) -> Self {
+        self.retry_on_status = Arc::new(retry_on_status);
+        self
+    }
+
+    #[inline]
+    fn retryable(&self, res: &io::Result<HttpClientResponse>) -> bool {
+        match res {
+            Ok(resp) => (self.retry_on_status)(resp.status),
+            Err(err) => (self.retry_on_error)(err),
+        }
+    }
+}
+
+#[cfg(feature = "sleep")]
+impl Default for HttpRetryPolicy {
+    /// Creates a policy that makes at most 3 attempts, waiting 100ms then 200ms, without budget.
+    ///
+    /// Every IO error is retried, as well as the status codes 408, 429 and 5xx.
+    fn default() -> Self {
+        Self {
+            backoff: Duration::from_millis(100),
+            budget: None,
+            max_attempts: 3,
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            retry_on_error: Arc::new(|_| true),
+            retry_on_status: Arc::new(|status| {
+                status == 408 || status == 429 || (500..600).contains(&status)
+            }),
+        }
+    }
+}
+
+// RetryingHttpClient
+
+/// Implementation of [`HttpClient`](trait.HttpClient.html) that retries requests with an
+/// exponential backoff.
+///
+/// Delays are waited using a [`Sleeper`](trait.Sleeper.html), so a
+/// [`FakeSleeper`](struct.FakeSleeper.html) can be used to avoid real waits in tests.
+///
+/// **This is supported on `feature=http-client,sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/http_client.rs).
+#[cfg(feature = "sleep")]
+pub struct RetryingHttpClient<CLIENT: HttpClient, SLEEPER: Sleeper> {
+    inner: CLIENT,
+    policy: HttpRetryPolicy,
+    sleeper: SLEEPER,
+}
+
+#[cfg(feature = "sleep")]
+impl<CLIENT: HttpClient, SLEEPER: Sleeper> RetryingHttpClient<CLIENT, SLEEPER> {
+    /// Creates a new client that delegates to `inner` according to the given policy.
+    pub fn new(inner: CLIENT, sleeper: SLEEPER, policy: HttpRetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            sleeper,
+        }
+    }
+}
+
+#[cfg(feature = "sleep")]
+#[async_trait]
+impl<CLIENT: HttpClient, SLEEPER: Sleeper> HttpClient for RetryingHttpClient<CLIENT, SLEEPER> {
+    async fn send(&self, req: HttpClientRequest) -> io::Result<HttpClientResponse> {
+        let mut attempt = 1;
+        let mut delay = self.policy.backoff;
+        let mut waited = Duration::ZERO;
+        loop {
+            let res = self.inner.send(req.clone()).await;
+            let exhausted = attempt >= self.policy.max_attempts
+                || self
+                    .policy
+                    .budget
+                    .map(|budget| waited + delay > budget)
+                    .unwrap_or_default();
+            if exhausted || !self.policy.retryable(&res) {
+                return res;
+            }
+            warn!(
+                attempt,
+                method = %req.method,
+                url = %req.url,
+                ?delay,
+                "request failed, retrying"
+            );
+            self.sleeper.sleep(delay).await;
+            waited += delay;
+            delay = delay
+                .saturating_mul(self.policy.multiplier)
+                .min(self.policy.max_backoff);
+            attempt += 1;
+        }
+    }
+}
+
+// Functions
+
+#[cfg(feature = "mock")]
+#[inline]
+fn matches_pattern(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match url.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(idx) => rest = &rest[idx + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.len() >= last.len() && rest.ends_with(last)
+        }
+        None => rest.is_empty(),
+    }
+}
+
+#[inline]
+fn reqwest_to_io_error(err: reqwest::Error) -> io::Error {
+    let kind = if err.is_timeout() {
+        io::ErrorKind::TimedOut
+    } else if err.is_connect() {
+        io::ErrorKind::ConnectionRefused
+    } else if err.is_builder() {
+        io::ErrorKind::InvalidInput
+    } else {
+        io::ErrorKind::Other
+    };
+    io::Error::new(kind, err)
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Mods
+
+    #[cfg(feature = "http")]
+    mod default_http_client {
+        use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+        use serde_json::{json, Value};
+
+        use crate::{DefaultHttpServer, HttpResponse, HttpServer};
+
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn send() {
+            let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+            let mut server = DefaultHttpServer::builder()
+                .route("POST", "/a", HttpResponse::Json(json!({ "foo": "bar" })))
+                .fallback(HttpResponse::Status(404))
+                .start(&addr)
+                .await
+                .expect("failed to start server");
+            let client = DefaultHttpClient::new();
+            let req = HttpClientRequest::post(format!("{}/a", server.url()))
+                .with_header("x-foo", "bar")
+                .with_json(&json!({ "id": 1 }))
+                .expect("failed to serialize body");
+            let resp = client.send(req).await.expect("failed to send request");
+            assert!(resp.is_success());
+            assert_eq!(resp.header("Content-Type"), Some("application/json"));
+            let body: Value = resp.json().expect("failed to deserialize body");
+            assert_eq!(body, json!({ "foo": "bar" }));
+            let req = server.next().await.expect("failed to receive request");
+            assert_eq!(req.header("x-foo"), Some("bar"));
+            assert_eq!(req.body, br#"{"id":1}"#);
+            let resp = client
+                .send(HttpClientRequest::get(format!("{}/b", server.url())))
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status, 404);
+            assert!(!resp.is_success());
+            server.next().await.expect("failed to receive request");
+            let url = server.url();
+            server.stop().await;
+            let err = client
+                .send(HttpClientRequest::get(url))
+                .await
+                .expect_err("request should fail after server is stopped");
+            assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    mod fake_http_client {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn send() {
+            let client = FakeHttpClient::new()
+                .with_response(
+                    "get",
+                    "https://api.example.com/users/*",
+                    HttpClientResponse::new(200).with_body("user"),
+                )
+                .with_response(
+                    "POST",
+                    "https://api.example.com/users",
+                    HttpClientResponse::new(201),
+                );
+            let resp = client
+                .send(HttpClientRequest::get("https://api.example.com/users/1"))
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.text().expect("failed to decode body"), "user");
+            let resp = client
+                .send(HttpClientRequest::post("https://api.example.com/users"))
+                .await
+                .expect("failed to send request");
+            assert_eq!(resp.status, 201);
+            client
+                .send(HttpClientRequest::delete("https://api.example.com/users/1"))
+                .await
+                .expect_err("request should be unexpected");
+            let urls: Vec<String> = client
+                .requests()
+                .into_iter()
+                .map(|req| format!("{} {}", req.method, req.url))
+                .collect();
+            assert_eq!(
+                urls,
+                vec![
+                    "GET https://api.example.com/users/1",
+                    "POST https://api.example.com/users",
+                    "DELETE https://api.example.com/users/1",
+                ]
+            );
+        }
+
+        #[test]
+        fn url_pattern() {
+            assert!(matches_pattern("https://a.com/*", "https://a.com/b"));
+            assert!(matches_pattern("https://*/b/*/c", "https://a.com/b/1/c"));
+            assert!(matches_pattern("https://a.com", "https://a.com"));
+            assert!(!matches_pattern("https://a.com", "https://a.com/b"));
+            assert!(!matches_pattern("https://a.com/*/c", "https://a.com/b"));
+        }
+    }
+
+    mod http_client_response {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn text() {
+            let resp = HttpClientResponse::new(200).with_body("hello");
+            assert_eq!(resp.text().expect("failed to decode body"), "hello");
+            let resp = HttpClientResponse::new(200).with_body(vec![0xff]);
+            assert!(resp.text().is_err());
+        }
+    }
+
+    #[cfg(all(feature = "mock", feature = "sleep"))]
+    mod retrying_http_client {
+        use crate::FakeSleeper;
+
+        use super::*;
+
+        // run
+
+        async fn run(
+            results: Vec<io::Result<u16>>,
+            policy: HttpRetryPolicy,
+        ) -> (io::Result<HttpClientResponse>, Vec<Duration>) {
+            let mut inner = MockHttpClient::new();
+            let mut seq = mockall::Sequence::new();
+            for res in results {
+                let mut res = Some(res);
+                inner
+                    .expect_send()
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .returning(move |_| {
+                        res.take()
+                            .expect("result should be returned once")
+                            .map(HttpClientResponse::new)
+                    });
+            }
+            let sleeper = FakeSleeper::new();
+            let client = RetryingHttpClient::new(inner, sleeper.clone(), policy);
+            let res = client
+                .send(HttpClientRequest::get("https://api.example.com"))
+                .await;
+            (res, sleeper.sleeps())
+        }
+
+        // Tests
+
+        #[tokio::test]
+        async fn budget() {
+            let policy = HttpRetryPolicy::default()
+                .with_budget(Duration::from_millis(250))
+                .with_max_attempts(5);
+            let (res, sleeps) = run(vec![Ok(503), Ok(502)], policy).await;
+            let resp = res.expect("failed to send request");
+            assert_eq!(resp.status, 502);
+            assert_eq!(sleeps, vec![Duration::from_millis(100)]);
+        }
+
+        #[tokio::test]
+        async fn error_not_retried() {
+            let policy = HttpRetryPolicy::default()
+                .with_retry_on_error(|err| err.kind() == io::ErrorKind::TimedOut);
+            let (res, sleeps) = run(
+                vec![
+                    Err(io::ErrorKind::TimedOut.into()),
+                    Err(io::ErrorKind::ConnectionRefused.into()),
+                ],
+                policy,
+            )
+            .await;
+            let err = res.expect_err("request should fail");
+            assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+            assert_eq!(sleeps, vec![Duration::from_millis(100)]);
+        }
+
+        #[tokio::test]
+        async fn exhausted() {
+            let (res, sleeps) = run(
+                vec![Ok(500), Err(io::ErrorKind::Other.into()), Ok(429)],
+                HttpRetryPolicy::default(),
+            )
+            .await;
+            let resp = res.expect("failed to send request");
+            assert_eq!(resp.status, 429);
+            assert_eq!(
+                sleeps,
+                vec![Duration::from_millis(100), Duration::from_millis(200)]
+            );
+        }
+
+        #[tokio::test]
+        async fn status_not_retried() {
+            let (res, sleeps) = run(vec![Ok(404)], HttpRetryPolicy::default()).await;
+            let resp = res.expect("failed to send request");
+            assert_eq!(resp.status, 404);
+            assert!(sleeps.is_empty());
+        }
+
+        #[tokio::test]
+        async fn success_after_retry() {
+            let (res, sleeps) = run(vec![Ok(503), Ok(200)], HttpRetryPolicy::default()).await;
+            let resp = res.expect("failed to send request");
+            assert!(resp.is_success());
+            assert_eq!(sleeps, vec![Duration::from_millis(100)]);
+        }
+    }
+}
@@ -0,0 +1,536 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::fs;
+
+use crate::Clock;
+
+type Entries = BTreeMap<String, (Option<DateTime<Utc>>, Vec<u8>)>;
+
+// Consts
+
+const DIR_SUFFIX: char = '_';
+// Even and small enough to leave room for the suffix of temporary files in the 255 bytes allowed
+// by most filesystems.
+const FILE_NAME_MAX_LEN: usize = 200;
+
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// KeyValueStore
+
+/// A trait for storing values by key.
+///
+/// **This is supported on `feature=kv` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/kv.rs).
+#[async_trait]
+pub trait KeyValueStore: Send + Sync {
+    /// Deletes the value of `key` and returns `true` if it existed.
+    async fn delete(&self, key: &str) -> io::Result<bool>;
+
+    /// Returns the value of `key` if it exists and is not expired.
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Returns the keys starting with `prefix`, sorted.
+    async fn list_prefix(&self, prefix: &str) -> io::Result<Vec<String>>;
+
+    /// Sets the value of `key`.
+    ///
+    /// If `ttl` is defined, the value expires after it.
+    async fn put(&self, key: &str, val: Vec<u8>, ttl: Option<Duration>) -> io::Result<()>;
+}
+
+// FileKeyValueStore
+
+/// Implementation of [`KeyValueStore`](trait.KeyValueStore.html) that stores each value in a file
+/// of a directory.
+///
+/// The file is named after the hex-encoded key. Long keys are split into nested directories to
+/// stay within the file name length limit of the filesystem.
+///
+/// Expired values are deleted when they are read.
+///
+/// **This is supported on `feature=kv` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/kv.rs).
+pub struct FileKeyValueStore<CLOCK: Clock> {
+    clock: CLOCK,
+    dir: PathBuf,
+}
+
+impl<CLOCK: Clock> FileKeyValueStore<CLOCK> {
+    /// Creates a new store in `dir`.
+    ///
+    /// The directory is created when the first value is stored.
+    pub fn new<P: Into<PathBuf>>(dir: P, clock: CLOCK) -> Self {
+        Self {
+            clock,
+            dir: dir.into(),
+        }
+    }
+
+    #[inline]
+    fn path(&self, key: &str) -> PathBuf {
+        let name = hex_encode(key);
+        let mut path = self.dir.clone();
+        let mut rest = name.as_str();
+        while rest.len() > FILE_NAME_MAX_LEN {
+            let (dir, tail) = rest.split_at(FILE_NAME_MAX_LEN);
+            path.push(format!("{dir}{DIR_SUFFIX}"));
+            rest = tail;
+        }
+        path.join(rest)
+    }
+
+    async fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = self.path(key);
+        let data = match fs::read(&path).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let (expiry, val) = decode_entry(data)?;
+        if is_expired(expiry, self.clock.utc()) {
+            remove_file(&path).await?;
+            Ok(None)
+        } else {
+            Ok(Some(val))
+        }
+    }
+}
+
+#[async_trait]
+impl<CLOCK: Clock> KeyValueStore for FileKeyValueStore<CLOCK> {
+    async fn delete(&self, key: &str) -> io::Result<bool> {
+        let exists = self.read(key).await?.is_some();
+        if exists {
+            remove_file(&self.path(key)).await?;
+        }
+        Ok(exists)
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        self.read(key).await
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut keys = vec![];
+        let mut dirs = vec![(self.dir.clone(), String::new())];
+        while let Some((dir, parent)) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let Some(name) = entry.file_name().to_str().map(String::from) else {
+                    continue;
+                };
+                if let Some(name) = name.strip_suffix(DIR_SUFFIX) {
+                    dirs.push((entry.path(), format!("{parent}{name}")));
+                } else if let Some(key) = hex_decode(&format!("{parent}{name}")) {
+                    if key.starts_with(prefix) && self.read(&key).await?.is_some() {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn put(&self, key: &str, val: Vec<u8>, ttl: Option<Duration>) -> io::Result<()> {
+        let expiry = ttl.map(|ttl| expiry(self.clock.utc(), ttl)).transpose()?;
+        let path = self.path(key);
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(dir).await?;
+        let tmp = dir.join(format!(
+            "{}.{}.tmp",
+            process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Err(err) = fs::write(&tmp, encode_entry(expiry, val)).await {
+            remove_file(&tmp).await.ok();
+            return Err(err);
+        }
+        fs::rename(&tmp, &path).await
+    }
+}
+
+// MemoryKeyValueStore
+
+/// Implementation of [`KeyValueStore`](trait.KeyValueStore.html) that stores values in memory.
+///
+/// Expired values are deleted when they are read.
+///
+/// **This is supported on `feature=kv` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/kv.rs).
+pub struct MemoryKeyValueStore<CLOCK: Clock> {
+    clock: CLOCK,
+    entries: Mutex<Entries>,
+}
+
+impl<CLOCK: Clock> MemoryKeyValueStore<CLOCK> {
+    /// Creates a new empty store.
+    pub fn new(clock: CLOCK) -> Self {
+        Self {
+            clock,
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[inline]
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = self.clock.utc();
+        entries.retain(|_, (expiry, _)| !is_expired(*expiry, now));
+        entries
+    }
+}
+
+#[async_trait]
+impl<CLOCK: Clock> KeyValueStore for MemoryKeyValueStore<CLOCK> {
+    async fn delete(&self, key: &str) -> io::Result<bool> {
+        Ok(self.entries().remove(key).is_some())
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.entries().get(key).map(|(_, val)| val.clone()))
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let keys = self
+            .entries()
+            .range(prefix.to_string()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        Ok(keys)
+    }
+
+    async fn put(&self, key: &str, val: Vec<u8>, ttl: Option<Duration>) -> io::Result<()> {
+        let expiry = ttl.map(|ttl| expiry(self.clock.utc(), ttl)).transpose()?;
+        self.entries().insert(key.into(), (expiry, val));
+        Ok(())
+    }
+}
+
+// RedisKeyValueStore
+
+/// Implementation of [`KeyValueStore`](trait.KeyValueStore.html) backed by Redis.
+///
+/// **This is supported on `feature=kv-redis` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/kv.rs).
+#[cfg(feature = "kv-redis")]
+pub struct RedisKeyValueStore(redis::aio::ConnectionManager);
+
+#[cfg(feature = "kv-redis")]
+impl RedisKeyValueStore {
+    /// Connects to the Redis server at `url` (like `redis://localhost:6379`).
+    pub async fn connect(url: &str) -> io::Result<Self> {
+        let client = redis::Client::open(url).map_err(redis_to_io_error)?;
+        let conn = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(redis_to_io_error)?;
+        Ok(Self(conn))
+    }
+
+    /// Creates a new store based on the given `redis` connection.
+    pub fn with_connection(conn: redis::aio::ConnectionManager) -> Self {
+        Self(conn)
+    }
+}
+
+#[cfg(feature = "kv-redis")]
+#[async_trait]
+impl KeyValueStore for RedisKeyValueStore {
+    async fn delete(&self, key: &str) -> io::Result<bool> {
+        let count: usize = redis::cmd("DEL")
+            .arg(key)
+            .query_async(&mut self.0.clone())
+            .await
+            .map_err(redis_to_io_error)?;
+        Ok(count > 0)
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.0.clone())
+            .await
+            .map_err(redis_to_io_error)
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut pattern = String::with_capacity(prefix.len() + 1);
+        for c in prefix.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+        let mut conn = self.0.clone();
+        let mut iter: redis::AsyncIter<String> = redis::cmd("SCAN")
+            .cursor_arg(0)
+            .arg("MATCH")
+            .arg(pattern)
+            .clone()
+            .iter_async(&mut conn)
+            .await
+            .map_err(redis_to_io_error)?;
+        let mut keys = vec![];
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    async fn put(&self, key: &str, val: Vec<u8>, ttl: Option<Duration>) -> io::Result<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(val);
+        if let Some(ttl) = ttl {
+            cmd.arg("PX").arg(ttl.as_millis().max(1) as u64);
+        }
+        cmd.query_async(&mut self.0.clone())
+            .await
+            .map_err(redis_to_io_error)
+    }
+}
+
+// MockKeyValueStore
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`KeyValueStore`](trait.KeyValueStore.html).
+    ///
+    /// **This is supported on `feature=kv,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/kv.rs).
+    pub KeyValueStore {}
+
+    #[async_trait]
+    impl KeyValueStore for KeyValueStore {
+        async fn delete(&self, key: &str) -> io::Result<bool>;
+
+        async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+        async fn list_prefix(&self, prefix: &str) -> io::Result<Vec<String>>;
+
+        async fn put(&self, key: &str, val: Vec<u8>, ttl: Option<Duration>) -> io::Result<()>;
+    }
+}
+
+// Functions
+
+fn decode_entry(mut data: Vec<u8>) -> io::Result<(Option<DateTime<Utc>>, Vec<u8>)> {
+    if data.len() < 9 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "entry is truncated",
+        ));
+    }
+    let mut millis = [0; 8];
+    millis.copy_from_slice(&data[1..9]);
+    let expiry = if data[0] == 0 {
+        None
+    } else {
+        let millis = i64::from_be_bytes(millis);
+        let expiry = DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid expiry"))?;
+        Some(expiry)
+    };
+    let val = data.split_off(9);
+    Ok((expiry, val))
+}
+
+fn encode_entry(expiry: Option<DateTime<Utc>>, val: Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(val.len() + 9);
+    data.push(expiry.is_some().into());
+    let millis = expiry
+        .map(|expiry| expiry.timestamp_millis())
+        .unwrap_or_default();
+    data.extend_from_slice(&millis.to_be_bytes());
+    data.extend(val);
+    data
+}
+
+#[inline]
+fn expiry(now: DateTime<Utc>, ttl: Duration) -> io::Result<DateTime<Utc>> {
+    chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "ttl is out of range"))
+}
+
+fn hex_decode(name: &str) -> Option<String> {
+    if !name.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..name.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(name.get(idx..idx + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+#[inline]
+fn hex_encode(key: &str) -> String {
+    key.bytes().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[inline]
+fn is_expired(expiry: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    expiry.map(|expiry| expiry <= now).unwrap_or_default()
+}
+
+#[cfg(feature = "kv-redis")]
+#[inline]
+fn redis_to_io_error(err: redis::RedisError) -> io::Error {
+    let kind = if err.is_timeout() {
+        io::ErrorKind::TimedOut
+    } else if err.is_connection_refusal() {
+        io::ErrorKind::ConnectionRefused
+    } else {
+        io::ErrorKind::Other
+    };
+    io::Error::new(kind, err)
+}
+
+#[inline]
+async fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use crate::FakeClock;
+
+    use super::*;
+
+    // run
+
+    async fn run(store: &dyn KeyValueStore, clock: &FakeClock) {
+        store
+            .put("user:1", b"alice".to_vec(), None)
+            .await
+            .expect("failed to put value");
+        store
+            .put("user:2", b"bob".to_vec(), Some(Duration::from_secs(60)))
+            .await
+            .expect("failed to put value");
+        store
+            .put("session:1", b"token".to_vec(), None)
+            .await
+            .expect("failed to put value");
+        let val = store.get("user:1").await.expect("failed to get value");
+        assert_eq!(val, Some(b"alice".to_vec()));
+        let keys = store
+            .list_prefix("user:")
+            .await
+            .expect("failed to list keys");
+        assert_eq!(keys, vec!["user:1", "user:2"]);
+        clock.advance(Duration::from_secs(60));
+        let val = store.get("user:2").await.expect("failed to get value");
+        assert!(val.is_none());
+        let keys = store
+            .list_prefix("user:")
+            .await
+            .expect("failed to list keys");
+        assert_eq!(keys, vec!["user:1"]);
+        let deleted = store
+            .delete("user:1")
+            .await
+            .expect("failed to delete value");
+        assert!(deleted);
+        let deleted = store
+            .delete("user:1")
+            .await
+            .expect("failed to delete value");
+        assert!(!deleted);
+        let keys = store.list_prefix("").await.expect("failed to list keys");
+        assert_eq!(keys, vec!["session:1"]);
+    }
+
+    // Mods
+
+    mod file_key_value_store {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn store() {
+            let dir = std::env::temp_dir().join(format!("mockable-kv-{}", std::process::id()));
+            let clock = FakeClock::new(Utc::now());
+            let store = FileKeyValueStore::new(&dir, clock.clone());
+            run(&store, &clock).await;
+            fs::remove_dir_all(&dir)
+                .await
+                .expect("failed to remove directory");
+        }
+
+        #[tokio::test]
+        async fn store_long_keys() {
+            let dir = std::env::temp_dir().join(format!("mockable-kv-long-{}", std::process::id()));
+            let clock = FakeClock::new(Utc::now());
+            let store = FileKeyValueStore::new(&dir, clock.clone());
+            let keys = [
+                "a".repeat(FILE_NAME_MAX_LEN / 2),
+                "a".repeat(FILE_NAME_MAX_LEN / 2 + 1),
+                "é".repeat(300),
+            ];
+            for key in &keys {
+                store
+                    .put(key, key.clone().into_bytes(), None)
+                    .await
+                    .expect("failed to put value");
+            }
+            for key in &keys {
+                let val = store.get(key).await.expect("failed to get value");
+                assert_eq!(val, Some(key.clone().into_bytes()));
+            }
+            let listed = store.list_prefix("a").await.expect("failed to list keys");
+            assert_eq!(listed, keys[..2]);
+            let listed = store.list_prefix("").await.expect("failed to list keys");
+            assert_eq!(listed, keys);
+            fs::remove_dir_all(&dir)
+                .await
+                .expect("failed to remove directory");
+        }
+    }
+
+    mod memory_key_value_store {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn store() {
+            let clock = FakeClock::new(Utc::now());
+            let store = MemoryKeyValueStore::new(clock.clone());
+            run(&store, &clock).await;
+        }
+    }
+}
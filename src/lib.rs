@@ -1,25 +1,101 @@
 // Re-use
 
-#[cfg(all(feature = "clock", feature = "mock"))]
-pub use self::clock::MockClock;
 #[cfg(feature = "clock")]
-pub use self::clock::{Clock, DefaultClock};
-#[cfg(all(feature = "cmd", feature = "mock"))]
-pub use self::cmd::MockCommandRunner;
+pub use self::clock::{Clock, DefaultClock, LocalTimezone, OffsetClock, QuantizedClock};
+#[cfg(all(feature = "clock", feature = "mock"))]
+pub use self::clock::{FakeClock, MockClock, SequenceClock};
+#[cfg(all(feature = "cmd", unix))]
+pub use self::cmd::ResourceLimits;
 #[cfg(feature = "cmd")]
-pub use self::cmd::{Command, CommandOutput, CommandRunner, DefaultCommandRunner};
-#[cfg(all(feature = "http", feature = "mock"))]
-pub use self::http::MockHttpServer;
+pub use self::cmd::{
+    ChildProcess, Command, CommandFailedError, CommandOutput, CommandParseError, CommandRunner,
+    CommandStdio, DefaultChildProcess, DefaultCommandRunner, DefaultSyncCommandRunner, ExitStatus,
+    MeteredCommandRunner, RetryPolicy, RetryingCommandRunner, SyncCommandRunner,
+    ThrottledCommandRunner,
+};
+#[cfg(all(feature = "cmd", feature = "mock"))]
+pub use self::cmd::{
+    CommandMatcher, FakeCommandRunner, MockChildProcess, MockCommandRunner, MockSyncCommandRunner,
+    RecordingCommandRunner,
+};
+#[cfg(all(feature = "download", feature = "mock"))]
+pub use self::download::MockDownloader;
+#[cfg(feature = "download")]
+pub use self::download::{DefaultDownloader, DownloadOptions, Downloader};
 #[cfg(feature = "http")]
-pub use self::http::{DefaultHttpServer, HttpRequest, HttpResponse, HttpServer};
+pub use self::http::{
+    DefaultHttpServer, DefaultHttpServerBuilder, HttpAuth, HttpCookie, HttpCors, HttpExpectation,
+    HttpFault, HttpInteraction, HttpOverflowPolicy, HttpPart, HttpRecordedResponse, HttpRequest,
+    HttpRequestMatcher, HttpResponse, HttpSameSite, HttpServer, HttpStopSummary, WebSocketMessage,
+    WebSocketServer,
+};
+#[cfg(feature = "http-record")]
+pub use self::http::{HttpCassette, HttpReplayRules};
+#[cfg(all(feature = "http", feature = "mock"))]
+pub use self::http::{MockHttpServer, MockWebSocketServer};
+#[cfg(feature = "http-client")]
+pub use self::http_client::{DefaultHttpClient, HttpClient, HttpClientRequest, HttpClientResponse};
+#[cfg(all(feature = "http-client", feature = "mock"))]
+pub use self::http_client::{FakeHttpClient, MockHttpClient};
+#[cfg(all(feature = "http-client", feature = "sleep"))]
+pub use self::http_client::{HttpRetryPolicy, RetryingHttpClient};
+#[cfg(all(feature = "kv", feature = "mock"))]
+pub use self::kv::MockKeyValueStore;
+#[cfg(feature = "kv-redis")]
+pub use self::kv::RedisKeyValueStore;
+#[cfg(feature = "kv")]
+pub use self::kv::{FileKeyValueStore, KeyValueStore, MemoryKeyValueStore};
 #[cfg(any(feature = "mock", test))]
 pub use self::mock::Mock;
-#[cfg(all(feature = "uuid", feature = "mock"))]
-pub use self::uuid::MockUuidGenerator;
+#[cfg(all(feature = "cron", feature = "mock"))]
+pub use self::schedule::MockSchedule;
+#[cfg(feature = "cron")]
+pub use self::schedule::{DefaultSchedule, Schedule};
+#[cfg(feature = "scheduler")]
+pub use self::scheduler::{DefaultScheduler, Scheduler, Task, TaskHandle};
+#[cfg(all(feature = "scheduler", feature = "mock"))]
+pub use self::scheduler::{FakeScheduler, MockScheduler};
+#[cfg(all(feature = "short-id", feature = "mock"))]
+pub use self::short_id::MockShortIdGenerator;
+#[cfg(feature = "short-id")]
+pub use self::short_id::{DefaultShortIdGenerator, ShortIdGenerator};
+#[cfg(all(feature = "sleep", feature = "clock"))]
+pub use self::sleep::Deadline;
+#[cfg(feature = "sleep")]
+pub use self::sleep::{
+    BlockingSleeper, DefaultBlockingSleeper, DefaultInterval, DefaultSleeper, Elapsed, Interval,
+    Sleeper, SleeperExt,
+};
+#[cfg(all(feature = "sleep", feature = "mock"))]
+pub use self::sleep::{
+    FakeBlockingSleeper, FakeSleeper, MockBlockingSleeper, MockInterval, MockSleeper,
+    TokioTestSleeper,
+};
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::{
+    DefaultSqliteClient, SqliteClient, SqliteRow, SqliteTransaction, SqliteValue,
+};
+#[cfg(all(feature = "sqlite", feature = "mock"))]
+pub use self::sqlite::{MockSqliteClient, MockSqliteTransaction};
+#[cfg(feature = "os-info")]
+pub use self::sys::OsInfo;
+#[cfg(feature = "terminal")]
+pub use self::terminal::{DefaultTerminal, Terminal, TerminalStream};
+#[cfg(all(feature = "terminal", feature = "mock"))]
+pub use self::terminal::{FakeTerminal, MockTerminal};
+#[cfg(all(feature = "uuid", feature = "clock", feature = "mock"))]
+pub use self::uuid::MockTimeUuidGenerator;
+#[cfg(all(feature = "uuid", feature = "clock"))]
+pub use self::uuid::{ClockUuidGenerator, TimeUuidGenerator};
 #[cfg(feature = "uuid")]
 pub use self::uuid::{DefaultUuidGenerator, UuidGenerator};
+#[cfg(all(feature = "uuid", feature = "mock"))]
+pub use self::uuid::{MockUuidGenerator, SeededUuidGenerator, SequentialUuidGenerator};
 #[cfg(feature = "mock")]
-pub use self::{env::MockEnv, sys::MockSystem};
+pub use self::{
+    env::MockEnv,
+    sys::{Exit, ExitRecorder, MockSystem},
+};
 pub use self::{
     env::{DefaultEnv, Env},
     sys::{DefaultSystem, System},
@@ -31,11 +107,29 @@ pub use self::{
 mod clock;
 #[cfg(feature = "cmd")]
 mod cmd;
+#[cfg(feature = "download")]
+mod download;
 mod env;
 #[cfg(feature = "http")]
 mod http;
+#[cfg(feature = "http-client")]
+mod http_client;
+#[cfg(feature = "kv")]
+mod kv;
 #[cfg(any(feature = "mock", test))]
 mod mock;
+#[cfg(feature = "cron")]
+mod schedule;
+#[cfg(feature = "scheduler")]
+mod scheduler;
+#[cfg(feature = "short-id")]
+mod short_id;
+#[cfg(feature = "sleep")]
+mod sleep;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod sys;
+#[cfg(feature = "terminal")]
+mod terminal;
 #[cfg(feature = "uuid")]
 mod uuid;
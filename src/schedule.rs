@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+
+// Schedule
+
+/// A trait for computing occurrences of a recurring schedule.
+///
+/// **This is supported on `feature=cron` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/schedule.rs).
+pub trait Schedule: Send + Sync {
+    /// Returns the first occurrence strictly after `after`.
+    ///
+    /// `None` is returned if there is no more occurrence.
+    fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>>;
+}
+
+// DefaultSchedule
+
+/// Default implementation of [`Schedule`](trait.Schedule.html) based on a cron expression.
+///
+/// See [`cron`](https://docs.rs/cron) for more details about the syntax.
+///
+/// **This is supported on `feature=cron` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/schedule.rs).
+#[derive(Debug, Clone)]
+pub struct DefaultSchedule(cron::Schedule);
+
+impl DefaultSchedule {
+    /// Parses a cron expression.
+    pub fn new(expr: &str) -> Result<Self, cron::error::Error> {
+        expr.parse()
+    }
+}
+
+impl FromStr for DefaultSchedule {
+    type Err = cron::error::Error;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        cron::Schedule::from_str(expr).map(Self)
+    }
+}
+
+impl Schedule for DefaultSchedule {
+    fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.0.after(&after).next()
+    }
+}
+
+// MockSchedule
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Schedule`](trait.Schedule.html).
+    ///
+    /// **This is supported on `feature=cron,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/schedule.rs).
+    pub Schedule {}
+
+    impl Schedule for Schedule {
+        fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>>;
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    // Mods
+
+    mod default_schedule {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn invalid() {
+            DefaultSchedule::new("0 0 25 * * *").expect_err("hour should be out of range");
+            DefaultSchedule::new("foo").expect_err("expression should be invalid");
+        }
+
+        #[test]
+        fn leap_day() {
+            let schedule = DefaultSchedule::new("0 0 0 29 2 *").expect("failed to parse schedule");
+            let after = Utc
+                .with_ymd_and_hms(2025, 3, 1, 0, 0, 0)
+                .single()
+                .expect("failed to create time");
+            let expected = Utc
+                .with_ymd_and_hms(2028, 2, 29, 0, 0, 0)
+                .single()
+                .expect("failed to create time");
+            assert_eq!(schedule.next_occurrence(after), Some(expected));
+        }
+
+        #[test]
+        fn no_more_occurrence() {
+            let schedule =
+                DefaultSchedule::new("0 0 0 1 1 * 2020").expect("failed to parse schedule");
+            let after = Utc
+                .with_ymd_and_hms(2025, 1, 1, 0, 0, 0)
+                .single()
+                .expect("failed to create time");
+            assert_eq!(schedule.next_occurrence(after), None);
+        }
+
+        #[test]
+        fn strictly_after() {
+            let schedule = DefaultSchedule::new("0 0 12 * * *").expect("failed to parse schedule");
+            let after = Utc
+                .with_ymd_and_hms(2025, 6, 1, 12, 0, 0)
+                .single()
+                .expect("failed to create time");
+            let expected = Utc
+                .with_ymd_and_hms(2025, 6, 2, 12, 0, 0)
+                .single()
+                .expect("failed to create time");
+            assert_eq!(schedule.next_occurrence(after), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,397 @@
+#[cfg(feature = "mock")]
+use std::sync::{Mutex, PoisonError};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::task::AbortHandle;
+
+#[cfg(feature = "mock")]
+use crate::FakeClock;
+use crate::{Clock, Sleeper};
+
+// Types
+
+/// A task run by a [`Scheduler`](trait.Scheduler.html).
+///
+/// **This is supported on `feature=scheduler` only.**
+pub type Task = Box<dyn FnMut() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+// Scheduler
+
+/// A trait for running tasks at a given time or periodically.
+///
+/// **This is supported on `feature=scheduler` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/scheduler.rs).
+pub trait Scheduler: Send + Sync {
+    /// Runs `task` once at `at`.
+    ///
+    /// If `at` is in the past, the task is run as soon as possible.
+    fn schedule_at(&self, at: DateTime<Utc>, task: Task) -> TaskHandle;
+
+    /// Runs `task` every `period`.
+    ///
+    /// The first run occurs after `period`.
+    ///
+    /// # Panics
+    /// Panics if `period` is zero.
+    fn schedule_every(&self, period: Duration, task: Task) -> TaskHandle;
+}
+
+// DefaultScheduler
+
+/// Default implementation of [`Scheduler`](trait.Scheduler.html).
+///
+/// Each task is spawned on the tokio runtime, so the methods must be called from within it.
+///
+/// **This is supported on `feature=scheduler` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/scheduler.rs).
+pub struct DefaultScheduler<CLOCK: Clock + 'static, SLEEPER: Sleeper + 'static> {
+    clock: Arc<CLOCK>,
+    sleeper: Arc<SLEEPER>,
+}
+
+impl<CLOCK: Clock + 'static, SLEEPER: Sleeper + 'static> DefaultScheduler<CLOCK, SLEEPER> {
+    /// Creates a new scheduler.
+    pub fn new(clock: CLOCK, sleeper: SLEEPER) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            sleeper: Arc::new(sleeper),
+        }
+    }
+}
+
+impl<CLOCK: Clock + 'static, SLEEPER: Sleeper + 'static> Scheduler
+    for DefaultScheduler<CLOCK, SLEEPER>
+{
+    fn schedule_at(&self, at: DateTime<Utc>, mut task: Task) -> TaskHandle {
+        let clock = self.clock.clone();
+        let sleeper = self.sleeper.clone();
+        let handle = tokio::spawn(async move {
+            let delay = (at - clock.utc()).to_std().unwrap_or_default();
+            if !delay.is_zero() {
+                sleeper.sleep(delay).await;
+            }
+            task().await;
+        });
+        TaskHandle::new(Some(handle.abort_handle()))
+    }
+
+    fn schedule_every(&self, period: Duration, mut task: Task) -> TaskHandle {
+        assert!(!period.is_zero(), "period must not be zero");
+        let sleeper = self.sleeper.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = sleeper.interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                task().await;
+            }
+        });
+        TaskHandle::new(Some(handle.abort_handle()))
+    }
+}
+
+// FakeScheduler
+
+/// Implementation of [`Scheduler`](trait.Scheduler.html) that runs the tasks when its clock is
+/// advanced.
+///
+/// Clones share the same tasks.
+///
+/// **This is supported on `feature=scheduler,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/scheduler.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct FakeScheduler {
+    clock: FakeClock,
+    tasks: Arc<Mutex<Vec<FakeTask>>>,
+}
+
+#[cfg(feature = "mock")]
+impl FakeScheduler {
+    /// Creates a new scheduler based on `clock`.
+    pub fn new(clock: FakeClock) -> Self {
+        Self {
+            clock,
+            tasks: Default::default(),
+        }
+    }
+
+    /// Advances the clock by `duration`.
+    ///
+    /// The due tasks are run in chronological order, the clock being set to their scheduled time
+    /// while they are running.
+    pub async fn advance(&self, duration: Duration) {
+        let end = self.clock.utc()
+            + chrono::Duration::from_std(duration).expect("duration is out of range");
+        while let Some(mut task) = self.next(end) {
+            if task.at > self.clock.utc() {
+                self.clock.set(task.at);
+            }
+            (task.task)().await;
+            if let Some(period) = task.period {
+                if !task.handle.is_cancelled() {
+                    task.at += chrono::Duration::from_std(period).expect("period is out of range");
+                    self.tasks
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push(task);
+                }
+            }
+        }
+        if end > self.clock.utc() {
+            self.clock.set(end);
+        }
+    }
+
+    /// Returns the number of tasks that are neither completed nor cancelled.
+    pub fn pending(&self) -> usize {
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|task| !task.handle.is_cancelled())
+            .count()
+    }
+
+    #[inline]
+    fn next(&self, end: DateTime<Utc>) -> Option<FakeTask> {
+        let mut tasks = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+        tasks.retain(|task| !task.handle.is_cancelled());
+        let idx = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.at <= end)
+            .min_by_key(|(_, task)| task.at)
+            .map(|(idx, _)| idx)?;
+        Some(tasks.remove(idx))
+    }
+
+    #[inline]
+    fn schedule(&self, at: DateTime<Utc>, period: Option<Duration>, task: Task) -> TaskHandle {
+        let handle = TaskHandle::new(None);
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(FakeTask {
+                at,
+                handle: handle.clone(),
+                period,
+                task,
+            });
+        handle
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Scheduler for FakeScheduler {
+    fn schedule_at(&self, at: DateTime<Utc>, task: Task) -> TaskHandle {
+        self.schedule(at, None, task)
+    }
+
+    fn schedule_every(&self, period: Duration, task: Task) -> TaskHandle {
+        assert!(!period.is_zero(), "period must not be zero");
+        let at =
+            self.clock.utc() + chrono::Duration::from_std(period).expect("period is out of range");
+        self.schedule(at, Some(period), task)
+    }
+}
+
+// FakeTask
+
+#[cfg(feature = "mock")]
+struct FakeTask {
+    at: DateTime<Utc>,
+    handle: TaskHandle,
+    period: Option<Duration>,
+    task: Task,
+}
+
+// TaskHandle
+
+/// A handle to cancel a task scheduled by a [`Scheduler`](trait.Scheduler.html).
+///
+/// Clones refer to the same task. Dropping the handle does not cancel the task.
+///
+/// **This is supported on `feature=scheduler` only.**
+#[derive(Clone, Debug)]
+pub struct TaskHandle {
+    abort: Option<AbortHandle>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    /// Creates a new handle.
+    ///
+    /// If `abort` is defined, it is aborted when the task is cancelled.
+    pub fn new(abort: Option<AbortHandle>) -> Self {
+        Self {
+            abort,
+            cancelled: Default::default(),
+        }
+    }
+
+    /// Cancels the task.
+    ///
+    /// If the task is running, it is stopped at its next `await`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(abort) = &self.abort {
+            abort.abort();
+        }
+    }
+
+    /// Returns `true` if the task was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// MockScheduler
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Scheduler`](trait.Scheduler.html).
+    ///
+    /// **This is supported on `feature=scheduler,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/scheduler.rs).
+    pub Scheduler {}
+
+    impl Scheduler for Scheduler {
+        fn schedule_at(&self, at: DateTime<Utc>, task: Task) -> TaskHandle;
+        fn schedule_every(&self, period: Duration, task: Task) -> TaskHandle;
+    }
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::FakeSleeper;
+
+    use super::*;
+
+    // Functions
+
+    fn counting_task(count: Arc<AtomicUsize>) -> Task {
+        Box::new(move || {
+            let count = count.clone();
+            Box::pin(async move {
+                count.fetch_add(1, Ordering::Relaxed);
+            })
+        })
+    }
+
+    // Mods
+
+    mod default_scheduler {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn schedule_at() {
+            let clock = FakeClock::new(Utc::now());
+            let at = clock.utc() + chrono::Duration::seconds(60);
+            let sleeper = FakeSleeper::new();
+            let scheduler = DefaultScheduler::new(clock, sleeper.clone());
+            let count = Arc::new(AtomicUsize::new(0));
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let task_count = count.clone();
+            let mut tx = Some(tx);
+            scheduler.schedule_at(
+                at,
+                Box::new(move || {
+                    task_count.fetch_add(1, Ordering::Relaxed);
+                    let tx = tx.take();
+                    Box::pin(async move {
+                        if let Some(tx) = tx {
+                            tx.send(()).ok();
+                        }
+                    })
+                }),
+            );
+            rx.await.expect("failed to receive");
+            assert_eq!(count.load(Ordering::Relaxed), 1);
+            assert_eq!(sleeper.sleeps(), vec![Duration::from_secs(60)]);
+        }
+
+        #[tokio::test]
+        #[should_panic(expected = "period must not be zero")]
+        async fn schedule_every_zero() {
+            let scheduler = DefaultScheduler::new(FakeClock::new(Utc::now()), FakeSleeper::new());
+            scheduler.schedule_every(Duration::ZERO, counting_task(Default::default()));
+        }
+    }
+
+    mod fake_scheduler {
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn cancel() {
+            let clock = FakeClock::new(Utc::now());
+            let scheduler = FakeScheduler::new(clock);
+            let count = Arc::new(AtomicUsize::new(0));
+            let handle =
+                scheduler.schedule_every(Duration::from_secs(10), counting_task(count.clone()));
+            scheduler.advance(Duration::from_secs(25)).await;
+            handle.cancel();
+            scheduler.advance(Duration::from_secs(60)).await;
+            assert_eq!(count.load(Ordering::Relaxed), 2);
+            assert_eq!(scheduler.pending(), 0);
+        }
+
+        #[tokio::test]
+        async fn schedule_at() {
+            let start = Utc::now();
+            let clock = FakeClock::new(start);
+            let scheduler = FakeScheduler::new(clock.clone());
+            let count = Arc::new(AtomicUsize::new(0));
+            scheduler.schedule_at(
+                start + chrono::Duration::seconds(30),
+                counting_task(count.clone()),
+            );
+            scheduler.advance(Duration::from_secs(29)).await;
+            assert_eq!(count.load(Ordering::Relaxed), 0);
+            assert_eq!(scheduler.pending(), 1);
+            scheduler.advance(Duration::from_secs(1)).await;
+            assert_eq!(count.load(Ordering::Relaxed), 1);
+            assert_eq!(scheduler.pending(), 0);
+            assert_eq!(clock.utc(), start + chrono::Duration::seconds(30));
+        }
+
+        #[tokio::test]
+        async fn schedule_every() {
+            let clock = FakeClock::new(Utc::now());
+            let scheduler = FakeScheduler::new(clock);
+            let count = Arc::new(AtomicUsize::new(0));
+            scheduler.schedule_every(Duration::from_secs(10), counting_task(count.clone()));
+            scheduler.advance(Duration::from_secs(35)).await;
+            assert_eq!(count.load(Ordering::Relaxed), 3);
+            assert_eq!(scheduler.pending(), 1);
+        }
+
+        #[test]
+        #[should_panic(expected = "period must not be zero")]
+        fn schedule_every_zero() {
+            let scheduler = FakeScheduler::new(FakeClock::new(Utc::now()));
+            scheduler.schedule_every(Duration::ZERO, counting_task(Default::default()));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use rand::Rng;
+
+// Consts
+
+const DEFAULT_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz-";
+const DEFAULT_LENGTH: usize = 21;
+
+// ShortIdGenerator
+
+/// A trait for generating short random IDs (like invite codes or order references).
+///
+/// **This is supported on `feature=short-id` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/short_id.rs).
+pub trait ShortIdGenerator: Send + Sync {
+    /// Generates a new ID.
+    fn generate(&self) -> String;
+}
+
+// DefaultShortIdGenerator
+
+/// Default implementation of [`ShortIdGenerator`](trait.ShortIdGenerator.html).
+///
+/// By default, IDs are 21 characters long and use the URL-safe alphabet `A-Za-z0-9_-`, like
+/// NanoID.
+///
+/// **This is supported on `feature=short-id` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/short_id.rs).
+#[derive(Clone, Debug)]
+pub struct DefaultShortIdGenerator {
+    alphabet: Vec<char>,
+    len: usize,
+}
+
+impl DefaultShortIdGenerator {
+    /// Creates a new generator with the default alphabet and length.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the characters used to generate IDs.
+    ///
+    /// # Panics
+    /// Panics if `alphabet` is empty.
+    pub fn with_alphabet(mut self, alphabet: &str) -> Self {
+        assert!(!alphabet.is_empty(), "alphabet must not be empty");
+        self.alphabet = alphabet.chars().collect();
+        self
+    }
+
+    /// Set the length of the generated IDs.
+    pub fn with_length(mut self, len: usize) -> Self {
+        self.len = len;
+        self
+    }
+}
+
+impl Default for DefaultShortIdGenerator {
+    fn default() -> Self {
+        Self {
+            alphabet: DEFAULT_ALPHABET.chars().collect(),
+            len: DEFAULT_LENGTH,
+        }
+    }
+}
+
+impl ShortIdGenerator for DefaultShortIdGenerator {
+    fn generate(&self) -> String {
+        let mut rng = rand::thread_rng();
+        (0..self.len)
+            .map(|_| self.alphabet[rng.gen_range(0..self.alphabet.len())])
+            .collect()
+    }
+}
+
+// MockShortIdGenerator
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`ShortIdGenerator`](trait.ShortIdGenerator.html).
+    ///
+    /// **This is supported on `feature=short-id,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/short_id.rs).
+    pub ShortIdGenerator {}
+
+    impl ShortIdGenerator for ShortIdGenerator {
+        fn generate(&self) -> String;
+    }
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod default_short_id_generator {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn generate() {
+            let id = DefaultShortIdGenerator::new().generate();
+            assert_eq!(id.len(), DEFAULT_LENGTH);
+            assert!(id.chars().all(|c| DEFAULT_ALPHABET.contains(c)));
+            let id = DefaultShortIdGenerator::new()
+                .with_alphabet("ABC")
+                .with_length(8)
+                .generate();
+            assert_eq!(id.len(), 8);
+            assert!(id.chars().all(|c| "ABC".contains(c)));
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    future::Future,
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "mock")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "clock")]
+use std::time::Instant;
+
+use async_trait::async_trait;
+
+#[cfg(feature = "clock")]
+use crate::Clock;
+#[cfg(feature = "mock")]
+use tokio::sync::Notify;
+
+// BlockingSleeper
+
+/// A trait for waiting in synchronous code.
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+pub trait BlockingSleeper: Send + Sync {
+    /// Blocks the current thread until `duration` has elapsed.
+    fn sleep(&self, duration: Duration);
+}
+
+// DefaultBlockingSleeper
+
+/// Default implementation of [`BlockingSleeper`](trait.BlockingSleeper.html).
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+pub struct DefaultBlockingSleeper;
+
+impl BlockingSleeper for DefaultBlockingSleeper {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// Deadline
+
+/// A point in time after which an operation should be abandoned.
+///
+/// It is based on the monotonic time of a [`Clock`](trait.Clock.html).
+///
+/// **This is supported on `feature=clock,sleep` only.**
+#[cfg(feature = "clock")]
+#[derive(Clone, Copy)]
+pub struct Deadline<'a> {
+    clock: &'a dyn Clock,
+    instant: Instant,
+}
+
+#[cfg(feature = "clock")]
+impl<'a> Deadline<'a> {
+    /// Creates a new deadline that expires once `duration` has elapsed.
+    pub fn after(clock: &'a dyn Clock, duration: Duration) -> Self {
+        Self {
+            clock,
+            instant: clock.instant() + duration,
+        }
+    }
+
+    /// Returns `true` if the deadline has expired.
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Returns the time remaining before the deadline expires.
+    ///
+    /// If the deadline has expired, zero is returned.
+    pub fn remaining(&self) -> Duration {
+        self.instant.saturating_duration_since(self.clock.instant())
+    }
+
+    /// Waits until the deadline expires.
+    pub async fn sleep_until_expired(&self, sleeper: &dyn Sleeper) {
+        let remaining = self.remaining();
+        if !remaining.is_zero() {
+            sleeper.sleep(remaining).await;
+        }
+    }
+}
+
+// Elapsed
+
+/// Error returned when a timeout has elapsed.
+///
+/// **This is supported on `feature=sleep` only.**
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Elapsed;
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+// Interval
+
+/// A trait for ticking at a fixed period.
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+#[async_trait]
+pub trait Interval: Send {
+    /// Waits until the next tick.
+    ///
+    /// The first tick completes immediately.
+    async fn tick(&mut self);
+}
+
+// DefaultInterval
+
+/// Default implementation of [`Interval`](trait.Interval.html).
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+pub struct DefaultInterval(tokio::time::Interval);
+
+#[async_trait]
+impl Interval for DefaultInterval {
+    async fn tick(&mut self) {
+        self.0.tick().await;
+    }
+}
+
+// Sleeper
+
+/// A trait for waiting.
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+#[async_trait]
+pub trait Sleeper: Send + Sync {
+    /// Returns a new interval that ticks every `period`.
+    fn interval(&self, period: Duration) -> Box<dyn Interval>;
+
+    /// Waits until `duration` has elapsed.
+    async fn sleep(&self, duration: Duration);
+}
+
+// SleeperExt
+
+/// Extension of [`Sleeper`](trait.Sleeper.html).
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+#[async_trait]
+pub trait SleeperExt: Sleeper {
+    /// Waits for `fut` to complete for at most `duration`.
+    ///
+    /// If `duration` elapses first, `fut` is dropped and an error is returned. If both complete at
+    /// the same time, the output of `fut` is returned.
+    async fn timeout<F: Future + Send>(
+        &self,
+        duration: Duration,
+        fut: F,
+    ) -> Result<F::Output, Elapsed>
+    where
+        F::Output: Send,
+    {
+        tokio::select! {
+            biased;
+            output = fut => Ok(output),
+            _ = self.sleep(duration) => Err(Elapsed),
+        }
+    }
+}
+
+impl<SLEEPER: Sleeper + ?Sized> SleeperExt for SLEEPER {}
+
+// DefaultSleeper
+
+/// Default implementation of [`Sleeper`](trait.Sleeper.html).
+///
+/// **This is supported on `feature=sleep` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+pub struct DefaultSleeper;
+
+#[async_trait]
+impl Sleeper for DefaultSleeper {
+    fn interval(&self, period: Duration) -> Box<dyn Interval> {
+        Box::new(DefaultInterval(tokio::time::interval(period)))
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+// FakeBlockingSleeper
+
+/// Implementation of [`BlockingSleeper`](trait.BlockingSleeper.html) that records the requested
+/// durations without waiting for them.
+///
+/// Clones share the same state.
+///
+/// **This is supported on `feature=sleep,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone, Default)]
+pub struct FakeBlockingSleeper(Arc<Mutex<Vec<Duration>>>);
+
+#[cfg(feature = "mock")]
+impl FakeBlockingSleeper {
+    /// Creates a new sleeper.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the durations of all sleeps, in the order in which they were requested.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[cfg(feature = "mock")]
+impl BlockingSleeper for FakeBlockingSleeper {
+    fn sleep(&self, duration: Duration) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(duration);
+    }
+}
+
+// FakeSleeper
+
+/// Implementation of [`Sleeper`](trait.Sleeper.html) that records the requested durations without
+/// waiting for them.
+///
+/// By default, sleeps and ticks complete immediately. If the sleeper is created with
+/// [`manual`](#method.manual), they complete when [`trigger`](#method.trigger) is called.
+///
+/// Clones share the same state.
+///
+/// **This is supported on `feature=sleep,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone, Default)]
+pub struct FakeSleeper(Arc<FakeSleeperState>);
+
+#[cfg(feature = "mock")]
+impl FakeSleeper {
+    /// Creates a new sleeper whose sleeps complete immediately.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new sleeper whose sleeps complete when [`trigger`](#method.trigger) is called.
+    pub fn manual() -> Self {
+        Self(Arc::new(FakeSleeperState {
+            manual: true,
+            ..Default::default()
+        }))
+    }
+
+    /// Returns the durations of all sleeps and ticks, in the order in which they were requested.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.0
+            .sleeps
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Completes all pending sleeps and ticks.
+    pub fn trigger(&self) {
+        self.0.notify.notify_waiters();
+    }
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl Sleeper for FakeSleeper {
+    fn interval(&self, period: Duration) -> Box<dyn Interval> {
+        Box::new(FakeInterval {
+            period,
+            sleeper: self.clone(),
+        })
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        let notified = self.0.notify.notified();
+        self.0
+            .sleeps
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(duration);
+        if self.0.manual {
+            notified.await;
+        }
+    }
+}
+
+// FakeInterval
+
+#[cfg(feature = "mock")]
+struct FakeInterval {
+    period: Duration,
+    sleeper: FakeSleeper,
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl Interval for FakeInterval {
+    async fn tick(&mut self) {
+        self.sleeper.sleep(self.period).await;
+    }
+}
+
+// FakeSleeperState
+
+#[cfg(feature = "mock")]
+#[derive(Default)]
+struct FakeSleeperState {
+    manual: bool,
+    notify: Notify,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+// TokioTestSleeper
+
+/// Implementation of [`Sleeper`](trait.Sleeper.html) that advances the paused clock of tokio
+/// instead of waiting.
+///
+/// The clock must be paused, for example with `#[tokio::test(start_paused = true)]`. It is useful
+/// to make code relying on `tokio::time` (like timeouts) observe the elapsed time instantly.
+///
+/// Note that [`DefaultSleeper`](struct.DefaultSleeper.html) also works with a paused clock: tokio
+/// auto-advances it when the runtime has no more work to do.
+///
+/// **This is supported on `feature=sleep,mock` only.**
+#[cfg(feature = "mock")]
+pub struct TokioTestSleeper;
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl Sleeper for TokioTestSleeper {
+    fn interval(&self, period: Duration) -> Box<dyn Interval> {
+        Box::new(TokioTestInterval {
+            first: true,
+            period,
+        })
+    }
+
+    /// # Panics
+    /// Panics if the clock is not paused.
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::advance(duration).await;
+    }
+}
+
+// TokioTestInterval
+
+#[cfg(feature = "mock")]
+struct TokioTestInterval {
+    first: bool,
+    period: Duration,
+}
+
+#[cfg(feature = "mock")]
+#[async_trait]
+impl Interval for TokioTestInterval {
+    async fn tick(&mut self) {
+        if self.first {
+            self.first = false;
+        } else {
+            tokio::time::advance(self.period).await;
+        }
+    }
+}
+
+// MockBlockingSleeper
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`BlockingSleeper`](trait.BlockingSleeper.html).
+    ///
+    /// **This is supported on `feature=sleep,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+    pub BlockingSleeper {}
+
+    impl BlockingSleeper for BlockingSleeper {
+        fn sleep(&self, duration: Duration);
+    }
+}
+
+// MockInterval
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Interval`](trait.Interval.html).
+    ///
+    /// **This is supported on `feature=sleep,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+    pub Interval {}
+
+    #[async_trait]
+    impl Interval for Interval {
+        async fn tick(&mut self);
+    }
+}
+
+// MockSleeper
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Sleeper`](trait.Sleeper.html).
+    ///
+    /// **This is supported on `feature=sleep,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sleep.rs).
+    pub Sleeper {}
+
+    #[async_trait]
+    impl Sleeper for Sleeper {
+        fn interval(&self, period: Duration) -> Box<dyn Interval>;
+        async fn sleep(&self, duration: Duration);
+    }
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+
+    // Mods
+
+    #[cfg(feature = "clock")]
+    mod deadline {
+        use chrono::Utc;
+
+        use crate::FakeClock;
+
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn test() {
+            let clock = FakeClock::new(Utc::now());
+            let sleeper = FakeSleeper::new();
+            let deadline = Deadline::after(&clock, Duration::from_secs(10));
+            clock.advance(Duration::from_secs(4));
+            assert!(!deadline.expired());
+            assert_eq!(deadline.remaining(), Duration::from_secs(6));
+            deadline.sleep_until_expired(&sleeper).await;
+            clock.advance(Duration::from_secs(6));
+            assert!(deadline.expired());
+            assert_eq!(deadline.remaining(), Duration::ZERO);
+            deadline.sleep_until_expired(&sleeper).await;
+            assert_eq!(sleeper.sleeps(), vec![Duration::from_secs(6)]);
+        }
+    }
+
+    mod fake_blocking_sleeper {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn test() {
+            let sleeper = FakeBlockingSleeper::new();
+            let clone = sleeper.clone();
+            BlockingSleeper::sleep(&sleeper, Duration::from_secs(3600));
+            BlockingSleeper::sleep(&clone, Duration::from_secs(60));
+            assert_eq!(
+                sleeper.sleeps(),
+                vec![Duration::from_secs(3600), Duration::from_secs(60)]
+            );
+        }
+    }
+
+    mod fake_sleeper {
+        use tokio::{spawn, task::yield_now};
+
+        use super::*;
+
+        // Tests
+
+        #[tokio::test]
+        async fn immediate() {
+            let sleeper = FakeSleeper::new();
+            sleeper.sleep(Duration::from_secs(3600)).await;
+            let mut interval = sleeper.interval(Duration::from_secs(60));
+            interval.tick().await;
+            assert_eq!(
+                sleeper.sleeps(),
+                vec![Duration::from_secs(3600), Duration::from_secs(60)]
+            );
+        }
+
+        #[tokio::test]
+        async fn manual() {
+            let sleeper = FakeSleeper::manual();
+            let task = spawn({
+                let sleeper = sleeper.clone();
+                async move { sleeper.sleep(Duration::from_secs(3600)).await }
+            });
+            while sleeper.sleeps().is_empty() {
+                yield_now().await;
+            }
+            assert!(!task.is_finished());
+            sleeper.trigger();
+            task.await.expect("failed to join task");
+        }
+
+        #[tokio::test]
+        async fn timeout() {
+            let sleeper = FakeSleeper::new();
+            let res = sleeper
+                .timeout(Duration::from_secs(1), std::future::pending::<()>())
+                .await;
+            assert_eq!(res, Err(Elapsed));
+        }
+
+        #[tokio::test]
+        async fn timeout_ready() {
+            let sleeper = FakeSleeper::new();
+            for _ in 0..100 {
+                let res = sleeper
+                    .timeout(Duration::from_secs(1), std::future::ready(()))
+                    .await;
+                assert_eq!(res, Ok(()));
+            }
+        }
+    }
+
+    mod tokio_test_sleeper {
+        use tokio::time::Instant;
+
+        use super::*;
+
+        // Tests
+
+        #[tokio::test(start_paused = true)]
+        async fn interval() {
+            let start = Instant::now();
+            let mut interval = TokioTestSleeper.interval(Duration::from_secs(60));
+            interval.tick().await;
+            assert_eq!(start.elapsed(), Duration::ZERO);
+            interval.tick().await;
+            interval.tick().await;
+            assert_eq!(start.elapsed(), Duration::from_secs(120));
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn sleep() {
+            let start = Instant::now();
+            TokioTestSleeper.sleep(Duration::from_secs(3600)).await;
+            assert_eq!(start.elapsed(), Duration::from_secs(3600));
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
+
+use rusqlite::{
+    ffi, params_from_iter,
+    types::{ToSqlOutput, Value, ValueRef},
+    Connection, Error, Result, ToSql,
+};
+
+// SqliteValue
+
+/// Value of a SQLite column or parameter.
+///
+/// **This is supported on `feature=sqlite` only.**
+#[derive(Clone, Debug, PartialEq)]
+pub enum SqliteValue {
+    /// Binary data.
+    Blob(Vec<u8>),
+    /// Signed integer.
+    Integer(i64),
+    /// `NULL`.
+    Null,
+    /// Floating point number.
+    Real(f64),
+    /// UTF-8 text.
+    Text(String),
+}
+
+impl SqliteValue {
+    /// Returns the value as bytes if it is a blob.
+    pub fn as_blob(&self) -> Option<&[u8]> {
+        match self {
+            Self::Blob(blob) => Some(blob),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float if it is a real or an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(i) => Some(*i as f64),
+            Self::Real(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an integer if it is an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string if it is a text.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is `NULL`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+}
+
+impl<T: Into<SqliteValue>> From<Option<T>> for SqliteValue {
+    fn from(val: Option<T>) -> Self {
+        val.map(Into::into).unwrap_or(Self::Null)
+    }
+}
+
+impl From<&str> for SqliteValue {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
+    }
+}
+
+impl From<String> for SqliteValue {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Vec<u8>> for SqliteValue {
+    fn from(blob: Vec<u8>) -> Self {
+        Self::Blob(blob)
+    }
+}
+
+impl From<bool> for SqliteValue {
+    fn from(b: bool) -> Self {
+        Self::Integer(b.into())
+    }
+}
+
+impl From<f64> for SqliteValue {
+    fn from(f: f64) -> Self {
+        Self::Real(f)
+    }
+}
+
+impl From<i32> for SqliteValue {
+    fn from(i: i32) -> Self {
+        Self::Integer(i.into())
+    }
+}
+
+impl From<i64> for SqliteValue {
+    fn from(i: i64) -> Self {
+        Self::Integer(i)
+    }
+}
+
+impl From<ValueRef<'_>> for SqliteValue {
+    fn from(val: ValueRef<'_>) -> Self {
+        match Value::from(val) {
+            Value::Blob(blob) => Self::Blob(blob),
+            Value::Integer(i) => Self::Integer(i),
+            Value::Null => Self::Null,
+            Value::Real(f) => Self::Real(f),
+            Value::Text(text) => Self::Text(text),
+        }
+    }
+}
+
+impl ToSql for SqliteValue {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let val = match self {
+            Self::Blob(blob) => ValueRef::Blob(blob),
+            Self::Integer(i) => ValueRef::Integer(*i),
+            Self::Null => ValueRef::Null,
+            Self::Real(f) => ValueRef::Real(*f),
+            Self::Text(text) => ValueRef::Text(text.as_bytes()),
+        };
+        Ok(ToSqlOutput::Borrowed(val))
+    }
+}
+
+// SqliteRow
+
+/// Row returned by a query.
+///
+/// Rows can be built from literal values to fake query results.
+///
+/// **This is supported on `feature=sqlite` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SqliteRow {
+    /// The columns, in order.
+    pub columns: Vec<(String, SqliteValue)>,
+}
+
+impl SqliteRow {
+    /// Creates a new row without column.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of the column `name`.
+    pub fn get(&self, name: &str) -> Option<&SqliteValue> {
+        self.columns
+            .iter()
+            .find(|(col, _)| col == name)
+            .map(|(_, val)| val)
+    }
+
+    /// Returns the value of the column at `idx`.
+    pub fn get_index(&self, idx: usize) -> Option<&SqliteValue> {
+        self.columns.get(idx).map(|(_, val)| val)
+    }
+
+    /// Adds a column.
+    pub fn with_column<N: Into<String>, V: Into<SqliteValue>>(mut self, name: N, val: V) -> Self {
+        self.columns.push((name.into(), val.into()));
+        self
+    }
+}
+
+// SqliteTransaction
+
+/// A trait for running statements inside a transaction.
+///
+/// **This is supported on `feature=sqlite` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+pub trait SqliteTransaction {
+    /// Executes the statement and returns the number of rows that were changed.
+    fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize>;
+
+    /// Executes the query and returns its rows.
+    fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>>;
+}
+
+// SqliteClient
+
+/// A trait for querying a SQLite database.
+///
+/// **This is supported on `feature=sqlite` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+pub trait SqliteClient: Send + Sync {
+    /// Executes the statement and returns the number of rows that were changed.
+    fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize>;
+
+    /// Executes the query and returns its rows.
+    fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>>;
+
+    /// Runs `f` inside a transaction.
+    ///
+    /// The transaction is committed if `f` succeeds, it is rolled back otherwise.
+    ///
+    /// Only the given [`SqliteTransaction`](trait.SqliteTransaction.html) must be used inside
+    /// `f`: with [`DefaultSqliteClient`](struct.DefaultSqliteClient.html), using the client
+    /// itself returns an error.
+    fn transaction(&self, f: &mut dyn FnMut(&dyn SqliteTransaction) -> Result<()>) -> Result<()>;
+}
+
+// DefaultSqliteClient
+
+/// Default implementation of [`SqliteClient`](trait.SqliteClient.html).
+///
+/// **This is supported on `feature=sqlite` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+pub struct DefaultSqliteClient {
+    conn: Mutex<Connection>,
+    tx_thread: Mutex<Option<ThreadId>>,
+}
+
+impl DefaultSqliteClient {
+    /// Opens the database at `path`, creating it if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Connection::open(path).map(Self::with_connection)
+    }
+
+    /// Opens a new in-memory database.
+    ///
+    /// It is destroyed when the client is dropped, which makes it handy for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Connection::open_in_memory().map(Self::with_connection)
+    }
+
+    /// Creates a new client based on the given `rusqlite` connection.
+    pub fn with_connection(conn: Connection) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+            tx_thread: Mutex::new(None),
+        }
+    }
+
+    #[inline]
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        // The connection is locked during the whole transaction, locking it again from the thread
+        // running the transaction would deadlock.
+        if *self.tx_thread() == Some(thread::current().id()) {
+            return Err(Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some("the client can't be used inside a transaction".into()),
+            ));
+        }
+        Ok(self.conn.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    #[inline]
+    fn tx_thread(&self) -> MutexGuard<'_, Option<ThreadId>> {
+        self.tx_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SqliteClient for DefaultSqliteClient {
+    fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize> {
+        execute(&*self.conn()?, sql, params)
+    }
+
+    fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>> {
+        query(&*self.conn()?, sql, params)
+    }
+
+    fn transaction(&self, f: &mut dyn FnMut(&dyn SqliteTransaction) -> Result<()>) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let _tx_thread = TransactionThread::enter(self);
+        f(&DefaultSqliteTransaction(&tx))?;
+        tx.commit()
+    }
+}
+
+// DefaultSqliteTransaction
+
+struct DefaultSqliteTransaction<'a>(&'a Connection);
+
+impl SqliteTransaction for DefaultSqliteTransaction<'_> {
+    fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize> {
+        execute(self.0, sql, params)
+    }
+
+    fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>> {
+        query(self.0, sql, params)
+    }
+}
+
+// TransactionThread
+
+struct TransactionThread<'a>(&'a DefaultSqliteClient);
+
+impl<'a> TransactionThread<'a> {
+    #[inline]
+    fn enter(client: &'a DefaultSqliteClient) -> Self {
+        *client.tx_thread() = Some(thread::current().id());
+        Self(client)
+    }
+}
+
+impl Drop for TransactionThread<'_> {
+    fn drop(&mut self) {
+        *self.0.tx_thread() = None;
+    }
+}
+
+// MockSqliteClient
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`SqliteClient`](trait.SqliteClient.html).
+    ///
+    /// **This is supported on `feature=sqlite,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+    pub SqliteClient {}
+
+    impl SqliteClient for SqliteClient {
+        fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize>;
+
+        fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>>;
+
+        fn transaction(
+            &self,
+            f: &mut dyn FnMut(&dyn SqliteTransaction) -> Result<()>,
+        ) -> Result<()>;
+    }
+}
+
+// MockSqliteTransaction
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`SqliteTransaction`](trait.SqliteTransaction.html).
+    ///
+    /// **This is supported on `feature=sqlite,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sqlite.rs).
+    pub SqliteTransaction {}
+
+    impl SqliteTransaction for SqliteTransaction {
+        fn execute(&self, sql: &str, params: &[SqliteValue]) -> Result<usize>;
+
+        fn query(&self, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>>;
+    }
+}
+
+// Functions
+
+#[inline]
+fn execute(conn: &Connection, sql: &str, params: &[SqliteValue]) -> Result<usize> {
+    conn.execute(sql, params_from_iter(params))
+}
+
+fn query(conn: &Connection, sql: &str, params: &[SqliteValue]) -> Result<Vec<SqliteRow>> {
+    let mut stmt = conn.prepare(sql)?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query(params_from_iter(params))?;
+    let mut res = vec![];
+    while let Some(row) = rows.next()? {
+        let columns = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| Ok((name.clone(), row.get_ref(idx)?.into())))
+            .collect::<Result<_>>()?;
+        res.push(SqliteRow { columns });
+    }
+    Ok(res)
+}
+
+// Tests
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod default_sqlite_client {
+        use super::*;
+
+        // run
+
+        fn client() -> DefaultSqliteClient {
+            let client = DefaultSqliteClient::open_in_memory().expect("failed to open database");
+            client
+                .execute("CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT)", &[])
+                .expect("failed to create table");
+            client
+        }
+
+        // Tests
+
+        #[test]
+        fn query() {
+            let client = client();
+            let changed = client
+                .execute(
+                    "INSERT INTO user (id, name) VALUES (?1, ?2), (?3, ?4)",
+                    &[1.into(), "alice".into(), 2.into(), None::<String>.into()],
+                )
+                .expect("failed to insert users");
+            assert_eq!(changed, 2);
+            let rows = client
+                .query("SELECT id, name FROM user ORDER BY id", &[])
+                .expect("failed to select users");
+            let expected = vec![
+                SqliteRow::new()
+                    .with_column("id", 1)
+                    .with_column("name", "alice"),
+                SqliteRow::new()
+                    .with_column("id", 2)
+                    .with_column("name", SqliteValue::Null),
+            ];
+            assert_eq!(rows, expected);
+        }
+
+        #[test]
+        fn transaction() {
+            let client = client();
+            client
+                .transaction(&mut |tx| {
+                    tx.execute("INSERT INTO user (id, name) VALUES (1, 'alice')", &[])?;
+                    Ok(())
+                })
+                .expect("failed to commit transaction");
+            client
+                .transaction(&mut |tx| {
+                    tx.execute("INSERT INTO user (id, name) VALUES (2, 'bob')", &[])?;
+                    Err(rusqlite::Error::QueryReturnedNoRows)
+                })
+                .expect_err("transaction should fail");
+            let rows = client
+                .query("SELECT name FROM user", &[])
+                .expect("failed to select users");
+            assert_eq!(rows, vec![SqliteRow::new().with_column("name", "alice")]);
+        }
+
+        #[test]
+        fn transaction_reentrant() {
+            let client = client();
+            client
+                .transaction(&mut |_| {
+                    let err = client
+                        .execute("INSERT INTO user (id, name) VALUES (1, 'alice')", &[])
+                        .expect_err("execute should fail");
+                    assert_eq!(err.sqlite_error_code(), Some(ffi::ErrorCode::ApiMisuse));
+                    client
+                        .query("SELECT name FROM user", &[])
+                        .expect_err("query should fail");
+                    client
+                        .transaction(&mut |_| Ok(()))
+                        .expect_err("transaction should fail");
+                    Ok(())
+                })
+                .expect("failed to commit transaction");
+            let rows = client
+                .query("SELECT name FROM user", &[])
+                .expect("failed to select users");
+            assert!(rows.is_empty());
+        }
+    }
+
+    mod sqlite_row {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn get() {
+            let row = SqliteRow::new()
+                .with_column("id", 1)
+                .with_column("score", 2.5);
+            assert_eq!(row.get("id").and_then(SqliteValue::as_i64), Some(1));
+            assert_eq!(row.get_index(1).and_then(SqliteValue::as_f64), Some(2.5));
+            assert!(row.get("name").is_none());
+        }
+    }
+}
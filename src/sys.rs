@@ -1,14 +1,132 @@
+#[cfg(feature = "hostname")]
+use std::ffi::OsString;
+#[cfg(feature = "mock")]
+use std::{
+    convert::Infallible,
+    panic::{catch_unwind, panic_any, resume_unwind, UnwindSafe},
+    sync::{Arc, Mutex, PoisonError},
+};
+use std::{
+    io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+// OsInfo
+
+/// Information about the operating system.
+///
+/// **This is supported on `feature=os-info` only.**
+#[cfg(feature = "os-info")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OsInfo {
+    /// The Linux distribution (like `Ubuntu` or `Alpine Linux`), `None` on other systems.
+    pub distro: Option<String>,
+    /// The name of the operating system (like `linux`, `macos` or `windows`).
+    pub name: String,
+    /// The version of the operating system, if it is known.
+    pub version: Option<String>,
+}
+
 // System
 
 /// A trait for interacting with the system.
 ///
 /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sys.rs).
 pub trait System: Send + Sync {
+    /// Returns the architecture of the CPU (like `x86_64` or `aarch64`).
+    fn arch(&self) -> String;
+
+    /// Returns the amount of memory available for new processes, in bytes.
+    ///
+    /// **This is supported on `feature=sysinfo` only.**
+    #[cfg(feature = "sysinfo")]
+    fn available_memory(&self) -> u64;
+
+    /// Returns the cache directory of the user (like `~/.cache` on Linux).
+    ///
+    /// **This is supported on `feature=dirs` only.**
+    #[cfg(feature = "dirs")]
+    fn cache_dir(&self) -> Option<PathBuf>;
+
+    /// Returns the configuration directory of the user (like `~/.config` on Linux).
+    ///
+    /// **This is supported on `feature=dirs` only.**
+    #[cfg(feature = "dirs")]
+    fn config_dir(&self) -> Option<PathBuf>;
+
+    /// Returns the current working directory.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+
+    /// Returns the path of the current executable.
+    fn current_exe(&self) -> io::Result<PathBuf>;
+
+    /// Returns the data directory of the user (like `~/.local/share` on Linux).
+    ///
+    /// **This is supported on `feature=dirs` only.**
+    #[cfg(feature = "dirs")]
+    fn data_dir(&self) -> Option<PathBuf>;
+
+    /// Terminates the current process with the given exit code.
+    ///
+    /// In tests, [`ExitRecorder`](struct.ExitRecorder.html) records the code and unwinds instead.
+    fn exit(&self, code: i32) -> !;
+
+    /// Returns the home directory of the user.
+    ///
+    /// **This is supported on `feature=dirs` only.**
+    #[cfg(feature = "dirs")]
+    fn home_dir(&self) -> Option<PathBuf>;
+
+    /// Returns the hostname of the machine.
+    ///
+    /// **This is supported on `feature=hostname` only.**
+    #[cfg(feature = "hostname")]
+    fn hostname(&self) -> io::Result<OsString>;
+
+    /// Returns the number of CPUs available to the current process.
+    fn num_cpus(&self) -> usize;
+
+    /// Returns information about the operating system.
+    ///
+    /// **This is supported on `feature=os-info` only.**
+    #[cfg(feature = "os-info")]
+    fn os(&self) -> OsInfo;
+
+    /// Open a file or a directory with the default application.
+    ///
+    /// **This is supported on `feature=browser` only.**
+    #[cfg(feature = "browser")]
+    fn open_path(&self, path: &Path) -> io::Result<()>;
+
     /// Open a URL in the default browser.
     ///
     /// **This is supported on `feature=browser` only.**
     #[cfg(feature = "browser")]
-    fn open_url(&self, url: &str) -> std::io::Result<()>;
+    fn open_url(&self, url: &str) -> io::Result<()>;
+
+    /// Open a file or a directory with the given application.
+    ///
+    /// **This is supported on `feature=browser` only.**
+    #[cfg(feature = "browser")]
+    fn open_with(&self, path: &Path, app: &str) -> io::Result<()>;
+
+    /// Returns the ID of the parent process.
+    ///
+    /// It is only available on Unix, `None` is returned on other platforms.
+    fn parent_pid(&self) -> Option<u32>;
+
+    /// Returns the ID of the current process.
+    fn pid(&self) -> u32;
+
+    /// Changes the current working directory to `path`.
+    fn set_current_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Returns the total amount of memory, in bytes.
+    ///
+    /// **This is supported on `feature=sysinfo` only.**
+    #[cfg(feature = "sysinfo")]
+    fn total_memory(&self) -> u64;
 }
 
 // DefaultSystem
@@ -19,10 +137,114 @@ pub trait System: Send + Sync {
 pub struct DefaultSystem;
 
 impl System for DefaultSystem {
+    fn arch(&self) -> String {
+        std::env::consts::ARCH.into()
+    }
+
+    #[cfg(feature = "sysinfo")]
+    fn available_memory(&self) -> u64 {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        sys.available_memory()
+    }
+
+    #[cfg(feature = "dirs")]
+    fn cache_dir(&self) -> Option<PathBuf> {
+        dirs::cache_dir()
+    }
+
+    #[cfg(feature = "dirs")]
+    fn config_dir(&self) -> Option<PathBuf> {
+        dirs::config_dir()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        std::env::current_dir()
+    }
+
+    fn current_exe(&self) -> io::Result<PathBuf> {
+        std::env::current_exe()
+    }
+
+    #[cfg(feature = "dirs")]
+    fn data_dir(&self) -> Option<PathBuf> {
+        dirs::data_dir()
+    }
+
+    fn exit(&self, code: i32) -> ! {
+        std::process::exit(code)
+    }
+
+    #[cfg(feature = "dirs")]
+    fn home_dir(&self) -> Option<PathBuf> {
+        dirs::home_dir()
+    }
+
+    #[cfg(feature = "hostname")]
+    fn hostname(&self) -> io::Result<OsString> {
+        hostname::get()
+    }
+
+    fn num_cpus(&self) -> usize {
+        std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1)
+    }
+
+    #[cfg(feature = "os-info")]
+    fn os(&self) -> OsInfo {
+        let info = os_info::get();
+        let name = std::env::consts::OS;
+        let version = match info.version() {
+            os_info::Version::Unknown => None,
+            version => Some(version.to_string()),
+        };
+        OsInfo {
+            distro: (name == "linux").then(|| info.os_type().to_string()),
+            name: name.into(),
+            version,
+        }
+    }
+
+    #[cfg(feature = "browser")]
+    fn open_path(&self, path: &Path) -> io::Result<()> {
+        open::that(path)
+    }
+
     #[cfg(feature = "browser")]
-    fn open_url(&self, url: &str) -> std::io::Result<()> {
+    fn open_url(&self, url: &str) -> io::Result<()> {
         open::that(url)
     }
+
+    #[cfg(feature = "browser")]
+    fn open_with(&self, path: &Path, app: &str) -> io::Result<()> {
+        open::with(path, app)
+    }
+
+    #[cfg(unix)]
+    fn parent_pid(&self) -> Option<u32> {
+        Some(std::os::unix::process::parent_id())
+    }
+
+    #[cfg(not(unix))]
+    fn parent_pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn pid(&self) -> u32 {
+        std::process::id()
+    }
+
+    fn set_current_dir(&self, path: &Path) -> io::Result<()> {
+        std::env::set_current_dir(path)
+    }
+
+    #[cfg(feature = "sysinfo")]
+    fn total_memory(&self) -> u64 {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        sys.total_memory()
+    }
 }
 
 // MockSystem
@@ -31,13 +253,359 @@ impl System for DefaultSystem {
 mockall::mock! {
     /// `mockall` implementation of [`System`](trait.System.html).
     ///
+    /// Since `mockall` can't mock methods that never return, the expectation of
+    /// [`exit`](trait.System.html#tymethod.exit) returns
+    /// [`Infallible`](https://doc.rust-lang.org/std/convert/enum.Infallible.html): it should
+    /// panic instead of terminating the test (like `returning(|code| panic!("exit({code})"))`).
+    ///
     /// **This is supported on `feature=mock` only.**
     ///
     /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/sys.rs).
     pub System {}
 
-    impl System for System {
+    impl MockableSystem for System {
+        fn arch(&self) -> String;
+
+        #[cfg(feature = "sysinfo")]
+        fn available_memory(&self) -> u64;
+
+        #[cfg(feature = "dirs")]
+        fn cache_dir(&self) -> Option<PathBuf>;
+
+        #[cfg(feature = "dirs")]
+        fn config_dir(&self) -> Option<PathBuf>;
+
+        fn current_dir(&self) -> io::Result<PathBuf>;
+
+        fn current_exe(&self) -> io::Result<PathBuf>;
+
+        #[cfg(feature = "dirs")]
+        fn data_dir(&self) -> Option<PathBuf>;
+
+        fn exit(&self, code: i32) -> Infallible;
+
+        #[cfg(feature = "dirs")]
+        fn home_dir(&self) -> Option<PathBuf>;
+
+        #[cfg(feature = "hostname")]
+        fn hostname(&self) -> io::Result<OsString>;
+
+        fn num_cpus(&self) -> usize;
+
+        #[cfg(feature = "os-info")]
+        fn os(&self) -> OsInfo;
+
         #[cfg(feature = "browser")]
-        fn open_url(&self, url: &str) -> std::io::Result<()>;
+        fn open_path(&self, path: &Path) -> io::Result<()>;
+
+        #[cfg(feature = "browser")]
+        fn open_url(&self, url: &str) -> io::Result<()>;
+
+        #[cfg(feature = "browser")]
+        fn open_with(&self, path: &Path, app: &str) -> io::Result<()>;
+
+        fn parent_pid(&self) -> Option<u32>;
+
+        fn pid(&self) -> u32;
+
+        fn set_current_dir(&self, path: &Path) -> io::Result<()>;
+
+        #[cfg(feature = "sysinfo")]
+        fn total_memory(&self) -> u64;
+    }
+}
+
+#[cfg(feature = "mock")]
+impl System for MockSystem {
+    fn arch(&self) -> String {
+        MockableSystem::arch(self)
+    }
+
+    #[cfg(feature = "sysinfo")]
+    fn available_memory(&self) -> u64 {
+        MockableSystem::available_memory(self)
+    }
+
+    #[cfg(feature = "dirs")]
+    fn cache_dir(&self) -> Option<PathBuf> {
+        MockableSystem::cache_dir(self)
+    }
+
+    #[cfg(feature = "dirs")]
+    fn config_dir(&self) -> Option<PathBuf> {
+        MockableSystem::config_dir(self)
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        MockableSystem::current_dir(self)
+    }
+
+    fn current_exe(&self) -> io::Result<PathBuf> {
+        MockableSystem::current_exe(self)
+    }
+
+    #[cfg(feature = "dirs")]
+    fn data_dir(&self) -> Option<PathBuf> {
+        MockableSystem::data_dir(self)
+    }
+
+    fn exit(&self, code: i32) -> ! {
+        match MockableSystem::exit(self, code) {}
+    }
+
+    #[cfg(feature = "dirs")]
+    fn home_dir(&self) -> Option<PathBuf> {
+        MockableSystem::home_dir(self)
+    }
+
+    #[cfg(feature = "hostname")]
+    fn hostname(&self) -> io::Result<OsString> {
+        MockableSystem::hostname(self)
+    }
+
+    fn num_cpus(&self) -> usize {
+        MockableSystem::num_cpus(self)
+    }
+
+    #[cfg(feature = "os-info")]
+    fn os(&self) -> OsInfo {
+        MockableSystem::os(self)
+    }
+
+    #[cfg(feature = "browser")]
+    fn open_path(&self, path: &Path) -> io::Result<()> {
+        MockableSystem::open_path(self, path)
+    }
+
+    #[cfg(feature = "browser")]
+    fn open_url(&self, url: &str) -> io::Result<()> {
+        MockableSystem::open_url(self, url)
+    }
+
+    #[cfg(feature = "browser")]
+    fn open_with(&self, path: &Path, app: &str) -> io::Result<()> {
+        MockableSystem::open_with(self, path, app)
+    }
+
+    fn parent_pid(&self) -> Option<u32> {
+        MockableSystem::parent_pid(self)
+    }
+
+    fn pid(&self) -> u32 {
+        MockableSystem::pid(self)
+    }
+
+    fn set_current_dir(&self, path: &Path) -> io::Result<()> {
+        MockableSystem::set_current_dir(self, path)
+    }
+
+    #[cfg(feature = "sysinfo")]
+    fn total_memory(&self) -> u64 {
+        MockableSystem::total_memory(self)
+    }
+}
+
+// Exit
+
+/// The panic payload used by [`ExitRecorder`](struct.ExitRecorder.html) to unwind instead of
+/// terminating the process.
+///
+/// **This is supported on `feature=mock` only.**
+#[cfg(feature = "mock")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Exit(pub i32);
+
+// ExitRecorder
+
+/// A recorder of the exit codes passed to [`System::exit`](trait.System.html#tymethod.exit).
+///
+/// Once installed on a [`MockSystem`](struct.MockSystem.html), each call to `exit` records its
+/// code and unwinds with an [`Exit`](struct.Exit.html) payload, which can be caught with
+/// [`catch`](#method.catch).
+///
+/// Clones share the same state.
+///
+/// **This is supported on `feature=mock` only.**
+#[cfg(feature = "mock")]
+#[derive(Clone, Debug, Default)]
+pub struct ExitRecorder(Arc<Mutex<Vec<i32>>>);
+
+#[cfg(feature = "mock")]
+impl ExitRecorder {
+    /// Creates a new recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` and returns the code of the exit it triggered, if any.
+    ///
+    /// Panics that are not exits are propagated.
+    pub fn catch<R>(f: impl FnOnce() -> R + UnwindSafe) -> Result<R, i32> {
+        catch_unwind(f).map_err(|payload| match payload.downcast::<Exit>() {
+            Ok(exit) => exit.0,
+            Err(payload) => resume_unwind(payload),
+        })
+    }
+
+    /// Returns the codes of all exits, in the order in which they were requested.
+    pub fn codes(&self) -> Vec<i32> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Sets the expectation of `exit` on the given mock.
+    ///
+    /// Like a process, a mock can exit only once: the unwinding poisons its expectations.
+    pub fn install(&self, sys: &mut MockSystem) {
+        let codes = self.0.clone();
+        sys.expect_exit().returning(move |code| {
+            codes
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(code);
+            panic_any(Exit(code))
+        });
+    }
+}
+
+// MockableSystem
+
+#[cfg(feature = "mock")]
+trait MockableSystem {
+    fn arch(&self) -> String;
+
+    #[cfg(feature = "sysinfo")]
+    fn available_memory(&self) -> u64;
+
+    #[cfg(feature = "dirs")]
+    fn cache_dir(&self) -> Option<PathBuf>;
+
+    #[cfg(feature = "dirs")]
+    fn config_dir(&self) -> Option<PathBuf>;
+
+    fn current_dir(&self) -> io::Result<PathBuf>;
+
+    fn current_exe(&self) -> io::Result<PathBuf>;
+
+    #[cfg(feature = "dirs")]
+    fn data_dir(&self) -> Option<PathBuf>;
+
+    fn exit(&self, code: i32) -> Infallible;
+
+    #[cfg(feature = "dirs")]
+    fn home_dir(&self) -> Option<PathBuf>;
+
+    #[cfg(feature = "hostname")]
+    fn hostname(&self) -> io::Result<OsString>;
+
+    fn num_cpus(&self) -> usize;
+
+    #[cfg(feature = "os-info")]
+    fn os(&self) -> OsInfo;
+
+    #[cfg(feature = "browser")]
+    fn open_path(&self, path: &Path) -> io::Result<()>;
+
+    #[cfg(feature = "browser")]
+    fn open_url(&self, url: &str) -> io::Result<()>;
+
+    #[cfg(feature = "browser")]
+    fn open_with(&self, path: &Path, app: &str) -> io::Result<()>;
+
+    fn parent_pid(&self) -> Option<u32>;
+
+    fn pid(&self) -> u32;
+
+    fn set_current_dir(&self, path: &Path) -> io::Result<()>;
+
+    #[cfg(feature = "sysinfo")]
+    fn total_memory(&self) -> u64;
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod default_system {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn arch() {
+            assert_eq!(DefaultSystem.arch(), std::env::consts::ARCH);
+        }
+
+        #[test]
+        #[cfg(feature = "os-info")]
+        fn os() {
+            let os = DefaultSystem.os();
+            assert_eq!(os.name, std::env::consts::OS);
+            assert_eq!(os.distro.is_some(), cfg!(target_os = "linux"));
+        }
+    }
+
+    mod exit_recorder {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn exit() {
+            let recorder = ExitRecorder::new();
+            for code in [2, 0] {
+                let mut sys = MockSystem::new();
+                recorder.clone().install(&mut sys);
+                let res = ExitRecorder::catch(|| System::exit(&sys, code));
+                assert_eq!(res, Err(code));
+            }
+            assert_eq!(recorder.codes(), vec![2, 0]);
+        }
+
+        #[test]
+        fn no_exit() {
+            let res = ExitRecorder::catch(|| 1);
+            assert_eq!(res, Ok(1));
+        }
+
+        #[test]
+        #[should_panic(expected = "boom")]
+        fn other_panic() {
+            let _ = ExitRecorder::catch(|| panic!("boom"));
+        }
+
+        #[test]
+        fn payload() {
+            let recorder = ExitRecorder::new();
+            let mut sys = MockSystem::new();
+            recorder.install(&mut sys);
+            let payload = catch_unwind(|| System::exit(&sys, 3)).expect_err("exit must unwind");
+            let exit = payload
+                .downcast::<Exit>()
+                .expect("failed to downcast payload");
+            assert_eq!(*exit, Exit(3));
+        }
+    }
+
+    mod mock_system {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        #[should_panic(expected = "exit(2)")]
+        fn exit() {
+            let mut sys = MockSystem::new();
+            sys.expect_exit()
+                .with(mockall::predicate::eq(2))
+                .returning(|code| panic!("exit({code})"));
+            System::exit(&sys, 2);
+        }
     }
 }
@@ -0,0 +1,285 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+#[cfg(feature = "mock")]
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+// TerminalStream
+
+/// Standard stream of the process.
+///
+/// **This is supported on `feature=terminal` only.**
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TerminalStream {
+    /// Standard error.
+    Stderr,
+    /// Standard input.
+    Stdin,
+    /// Standard output.
+    Stdout,
+}
+
+// Terminal
+
+/// A trait for interacting with the user through the terminal.
+///
+/// **This is supported on `feature=terminal` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/terminal.rs).
+pub trait Terminal: Send + Sync {
+    /// Asks the user to confirm `msg` and returns `true` if the answer is `y` or `yes` (case
+    /// insensitive).
+    fn confirm(&self, msg: &str) -> io::Result<bool>;
+
+    /// Returns `true` if `stream` is connected to a terminal.
+    fn is_tty(&self, stream: TerminalStream) -> bool;
+
+    /// Displays `msg` and returns the line entered by the user, without the trailing newline.
+    fn prompt(&self, msg: &str) -> io::Result<String>;
+
+    /// Displays `msg` and returns the line entered by the user without echoing it.
+    fn prompt_password(&self, msg: &str) -> io::Result<String>;
+
+    /// Returns the size of the terminal as `(columns, rows)`.
+    fn size(&self) -> io::Result<(u16, u16)>;
+}
+
+// DefaultTerminal
+
+/// Default implementation of [`Terminal`](trait.Terminal.html).
+///
+/// Prompts are written to the standard error.
+///
+/// **This is supported on `feature=terminal` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/terminal.rs).
+pub struct DefaultTerminal;
+
+impl Terminal for DefaultTerminal {
+    fn confirm(&self, msg: &str) -> io::Result<bool> {
+        self.prompt(&format!("{msg} [y/N] "))
+            .map(|answer| is_confirmation(&answer))
+    }
+
+    fn is_tty(&self, stream: TerminalStream) -> bool {
+        match stream {
+            TerminalStream::Stderr => io::stderr().is_terminal(),
+            TerminalStream::Stdin => io::stdin().is_terminal(),
+            TerminalStream::Stdout => io::stdout().is_terminal(),
+        }
+    }
+
+    fn prompt(&self, msg: &str) -> io::Result<String> {
+        let mut stderr = io::stderr().lock();
+        stderr.write_all(msg.as_bytes())?;
+        stderr.flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let len = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(len);
+        Ok(line)
+    }
+
+    fn prompt_password(&self, msg: &str) -> io::Result<String> {
+        rpassword::prompt_password(msg)
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        crossterm::terminal::size()
+    }
+}
+
+// FakeTerminal
+
+/// Implementation of [`Terminal`](trait.Terminal.html) that replays scripted answers and records
+/// the prompts.
+///
+/// Answers are consumed in order by [`confirm`](trait.Terminal.html#tymethod.confirm),
+/// [`prompt`](trait.Terminal.html#tymethod.prompt) and
+/// [`prompt_password`](trait.Terminal.html#tymethod.prompt_password). When they are exhausted,
+/// an [`UnexpectedEof`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.UnexpectedEof)
+/// error is returned, like when the standard input is closed.
+///
+/// By default, no stream is a TTY and the size is 80x24.
+///
+/// Clones share the same state.
+///
+/// **This is supported on `feature=terminal,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/terminal.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct FakeTerminal(Arc<Mutex<FakeTerminalState>>);
+
+#[cfg(feature = "mock")]
+impl FakeTerminal {
+    /// Creates a new terminal without answer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the messages displayed so far, in order.
+    pub fn prompts(&self) -> Vec<String> {
+        self.state().prompts.clone()
+    }
+
+    /// Adds an answer.
+    pub fn with_answer<S: Into<String>>(self, answer: S) -> Self {
+        self.state().answers.push_back(answer.into());
+        self
+    }
+
+    /// Set the size of the terminal.
+    pub fn with_size(self, cols: u16, rows: u16) -> Self {
+        self.state().size = (cols, rows);
+        self
+    }
+
+    /// Set `stream` as connected to a terminal.
+    pub fn with_tty(self, stream: TerminalStream) -> Self {
+        self.state().ttys.push(stream);
+        self
+    }
+
+    #[inline]
+    fn answer(&self, msg: &str) -> io::Result<String> {
+        let mut state = self.state();
+        state.prompts.push(msg.into());
+        state
+            .answers
+            .pop_front()
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, FakeTerminalState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Default for FakeTerminal {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(FakeTerminalState {
+            answers: VecDeque::new(),
+            prompts: vec![],
+            size: (80, 24),
+            ttys: vec![],
+        })))
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Terminal for FakeTerminal {
+    fn confirm(&self, msg: &str) -> io::Result<bool> {
+        self.answer(msg).map(|answer| is_confirmation(&answer))
+    }
+
+    fn is_tty(&self, stream: TerminalStream) -> bool {
+        self.state().ttys.contains(&stream)
+    }
+
+    fn prompt(&self, msg: &str) -> io::Result<String> {
+        self.answer(msg)
+    }
+
+    fn prompt_password(&self, msg: &str) -> io::Result<String> {
+        self.answer(msg)
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.state().size)
+    }
+}
+
+// FakeTerminalState
+
+#[cfg(feature = "mock")]
+struct FakeTerminalState {
+    answers: VecDeque<String>,
+    prompts: Vec<String>,
+    size: (u16, u16),
+    ttys: Vec<TerminalStream>,
+}
+
+// MockTerminal
+
+#[cfg(feature = "mock")]
+mockall::mock! {
+    /// `mockall` implementation of [`Terminal`](trait.Terminal.html).
+    ///
+    /// **This is supported on `feature=terminal,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/terminal.rs).
+    pub Terminal {}
+
+    impl Terminal for Terminal {
+        fn confirm(&self, msg: &str) -> io::Result<bool>;
+
+        fn is_tty(&self, stream: TerminalStream) -> bool;
+
+        fn prompt(&self, msg: &str) -> io::Result<String>;
+
+        fn prompt_password(&self, msg: &str) -> io::Result<String>;
+
+        fn size(&self) -> io::Result<(u16, u16)>;
+    }
+}
+
+// Functions
+
+#[inline]
+fn is_confirmation(answer: &str) -> bool {
+    let answer = answer.trim();
+    answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+
+    // Mods
+
+    mod fake_terminal {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn script() {
+            let term = FakeTerminal::new()
+                .with_answer("alice")
+                .with_answer("secret")
+                .with_answer(" Yes ")
+                .with_answer("n")
+                .with_tty(TerminalStream::Stdin);
+            assert_eq!(
+                term.prompt("Username: ").expect("failed to prompt"),
+                "alice"
+            );
+            assert_eq!(
+                term.prompt_password("Password: ")
+                    .expect("failed to prompt"),
+                "secret"
+            );
+            assert!(term.confirm("Delete?").expect("failed to confirm"));
+            assert!(!term.confirm("Really?").expect("failed to confirm"));
+            let err = term
+                .prompt("Again: ")
+                .expect_err("answers should be exhausted");
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+            assert_eq!(
+                term.prompts(),
+                vec!["Username: ", "Password: ", "Delete?", "Really?", "Again: "]
+            );
+            assert!(term.is_tty(TerminalStream::Stdin));
+            assert!(!term.is_tty(TerminalStream::Stdout));
+            assert_eq!(term.size().expect("failed to get size"), (80, 24));
+        }
+    }
+}
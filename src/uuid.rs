@@ -1,4 +1,25 @@
+#[cfg(feature = "mock")]
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+#[cfg(feature = "clock")]
+use std::sync::{Mutex, PoisonError};
+
 use uuid::Uuid;
+#[cfg(feature = "mock")]
+use uuid::{Builder, Variant, Version};
+
+#[cfg(feature = "clock")]
+use crate::Clock;
+
+// Consts
+
+#[cfg(feature = "clock")]
+const GREGORIAN_OFFSET: u64 = 0x01b2_1dd2_1381_4000;
+
+#[cfg(feature = "mock")]
+const SPLITMIX64_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
 
 // UuidGenerator
 
@@ -12,6 +33,24 @@ pub trait UuidGenerator: Send + Sync {
     fn generate_v4(&self) -> Uuid;
 }
 
+// TimeUuidGenerator
+
+/// A trait for generating time-based UUIDs.
+///
+/// **This is supported on `feature=uuid,clock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/uuid.rs).
+#[cfg(feature = "clock")]
+pub trait TimeUuidGenerator: Send + Sync {
+    /// Generates a new UUID V1.
+    fn generate_v1(&self) -> Uuid;
+
+    /// Generates a new UUID V6.
+    ///
+    /// Unlike V1, UUIDs V6 are sortable by time.
+    fn generate_v6(&self) -> Uuid;
+}
+
 // DefaultUuidGenerator
 
 /// Default implementation of [`UuidGenerator`](trait.UuidGenerator.html).
@@ -27,7 +66,188 @@ impl UuidGenerator for DefaultUuidGenerator {
     }
 }
 
-// MockClock
+// ClockUuidGenerator
+
+/// Implementation of [`TimeUuidGenerator`](trait.TimeUuidGenerator.html) whose timestamps are
+/// sourced from a [`Clock`](trait.Clock.html).
+///
+/// The clock sequence is incremented each time the timestamp doesn't move forward, so UUIDs
+/// generated with a frozen clock are still unique.
+///
+/// **This is supported on `feature=uuid,clock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/uuid.rs).
+#[cfg(feature = "clock")]
+pub struct ClockUuidGenerator<CLOCK: Clock> {
+    clock: CLOCK,
+    node_id: [u8; 6],
+    state: Mutex<(u64, u16)>,
+}
+
+#[cfg(feature = "clock")]
+impl<CLOCK: Clock> ClockUuidGenerator<CLOCK> {
+    /// Creates a new generator with the given node ID (usually a MAC address).
+    pub fn new(clock: CLOCK, node_id: [u8; 6]) -> Self {
+        Self {
+            clock,
+            node_id,
+            state: Mutex::new((0, 0)),
+        }
+    }
+
+    #[inline]
+    fn generate(&self, version: u8) -> Uuid {
+        let now = self.clock.utc();
+        let ts = (now.timestamp() as i128 * 10_000_000
+            + now.timestamp_subsec_nanos() as i128 / 100
+            + GREGORIAN_OFFSET as i128)
+            .clamp(0, (1 << 60) - 1) as u64;
+        let seq = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if ts <= state.0 {
+                state.1 = state.1.wrapping_add(1) & 0x3fff;
+            }
+            state.0 = ts;
+            state.1
+        };
+        let (high, mid, low) = match version {
+            1 => (ts as u32, (ts >> 32) as u16, (ts >> 48) as u16 & 0x0fff),
+            _ => ((ts >> 28) as u32, (ts >> 12) as u16, ts as u16 & 0x0fff),
+        };
+        let mut bytes = [0; 16];
+        bytes[..4].copy_from_slice(&high.to_be_bytes());
+        bytes[4..6].copy_from_slice(&mid.to_be_bytes());
+        bytes[6..8].copy_from_slice(&(low | (version as u16) << 12).to_be_bytes());
+        bytes[8..10].copy_from_slice(&(seq | 0x8000).to_be_bytes());
+        bytes[10..].copy_from_slice(&self.node_id);
+        Uuid::from_bytes(bytes)
+    }
+}
+
+#[cfg(feature = "clock")]
+impl<CLOCK: Clock> TimeUuidGenerator for ClockUuidGenerator<CLOCK> {
+    fn generate_v1(&self) -> Uuid {
+        self.generate(1)
+    }
+
+    fn generate_v6(&self) -> Uuid {
+        self.generate(6)
+    }
+}
+
+// SeededUuidGenerator
+
+/// Implementation of [`UuidGenerator`](trait.UuidGenerator.html) that returns a deterministic
+/// stream of random-looking UUIDs V4.
+///
+/// Two generators created with the same seed return the same UUIDs.
+///
+/// Clones share the same stream.
+///
+/// **This is supported on `feature=uuid,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/uuid.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone)]
+pub struct SeededUuidGenerator(Arc<AtomicU64>);
+
+#[cfg(feature = "mock")]
+impl SeededUuidGenerator {
+    /// Creates a new generator from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(seed)))
+    }
+
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        let state = self
+            .0
+            .fetch_add(SPLITMIX64_GAMMA, Ordering::Relaxed)
+            .wrapping_add(SPLITMIX64_GAMMA);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(feature = "mock")]
+impl UuidGenerator for SeededUuidGenerator {
+    fn generate_v4(&self) -> Uuid {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_be_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_be_bytes());
+        Builder::from_bytes(bytes)
+            .set_variant(Variant::RFC4122)
+            .set_version(Version::Random)
+            .build()
+    }
+}
+
+// SequentialUuidGenerator
+
+/// Implementation of [`UuidGenerator`](trait.UuidGenerator.html) that returns deterministic UUIDs.
+///
+/// By default, it returns UUIDs from an incrementing counter
+/// (`00000000-0000-0000-0000-000000000001`, `00000000-0000-0000-0000-000000000002`...). If it is
+/// created with [`replaying`](#method.replaying), it returns the given UUIDs.
+///
+/// Clones share the same sequence.
+///
+/// **This is supported on `feature=uuid,mock` only.**
+///
+/// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/uuid.rs).
+#[cfg(feature = "mock")]
+#[derive(Clone, Default)]
+pub struct SequentialUuidGenerator(Arc<SequentialUuidGeneratorState>);
+
+#[cfg(feature = "mock")]
+impl SequentialUuidGenerator {
+    /// Creates a new generator returning UUIDs from an incrementing counter starting at 1.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new generator returning the given UUIDs.
+    ///
+    /// When the list is exhausted, the generator panics.
+    pub fn replaying(uuids: Vec<Uuid>) -> Self {
+        Self(Arc::new(SequentialUuidGeneratorState {
+            idx: AtomicUsize::new(0),
+            uuids: Some(uuids),
+        }))
+    }
+
+    /// Returns the number of UUIDs generated.
+    pub fn count(&self) -> usize {
+        self.0.idx.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "mock")]
+impl UuidGenerator for SequentialUuidGenerator {
+    fn generate_v4(&self) -> Uuid {
+        let idx = self.0.idx.fetch_add(1, Ordering::Relaxed);
+        match &self.0.uuids {
+            Some(uuids) => match uuids.get(idx) {
+                Some(uuid) => *uuid,
+                None => panic!("SequentialUuidGenerator called more times than expected"),
+            },
+            None => Uuid::from_u128(idx as u128 + 1),
+        }
+    }
+}
+
+// SequentialUuidGeneratorState
+
+#[cfg(feature = "mock")]
+#[derive(Default)]
+struct SequentialUuidGeneratorState {
+    idx: AtomicUsize,
+    uuids: Option<Vec<Uuid>>,
+}
+
+// MockUuidGenerator
 
 #[cfg(feature = "mock")]
 mockall::mock! {
@@ -42,3 +262,107 @@ mockall::mock! {
         fn generate_v4(&self) -> Uuid;
     }
 }
+
+// MockTimeUuidGenerator
+
+#[cfg(all(feature = "clock", feature = "mock"))]
+mockall::mock! {
+    /// `mockall` implementation of [`TimeUuidGenerator`](trait.TimeUuidGenerator.html).
+    ///
+    /// **This is supported on `feature=uuid,clock,mock` only.**
+    ///
+    /// [Example](https://github.com/leroyguillaume/mockable/tree/main/examples/uuid.rs).
+    pub TimeUuidGenerator {}
+
+    impl TimeUuidGenerator for TimeUuidGenerator {
+        fn generate_v1(&self) -> Uuid;
+
+        fn generate_v6(&self) -> Uuid;
+    }
+}
+
+// Tests
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+
+    // Mods
+
+    #[cfg(feature = "clock")]
+    mod clock_uuid_generator {
+        use chrono::{TimeZone, Utc};
+
+        use crate::FakeClock;
+
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn generate() {
+            let epoch = Utc
+                .timestamp_opt(0, 0)
+                .single()
+                .expect("failed to create time");
+            let clock = FakeClock::new(epoch);
+            let gen = ClockUuidGenerator::new(clock.clone(), [1, 2, 3, 4, 5, 6]);
+            assert_eq!(
+                gen.generate_v1().to_string(),
+                "13814000-1dd2-11b2-8000-010203040506"
+            );
+            assert_eq!(
+                gen.generate_v6().to_string(),
+                "1b21dd21-3814-6000-8001-010203040506"
+            );
+            let first = gen.generate_v6();
+            clock.advance(std::time::Duration::from_secs(1));
+            let second = gen.generate_v6();
+            assert!(first < second);
+            assert_eq!(second.get_version_num(), 6);
+        }
+    }
+
+    mod seeded_uuid_generator {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn generate_v4() {
+            let gen = SeededUuidGenerator::new(42);
+            let uuids = [gen.generate_v4(), gen.generate_v4()];
+            assert_ne!(uuids[0], uuids[1]);
+            assert_eq!(uuids[0].get_version(), Some(Version::Random));
+            assert_eq!(uuids[0].get_variant(), Some(Variant::RFC4122));
+            let gen = SeededUuidGenerator::new(42);
+            assert_eq!([gen.generate_v4(), gen.generate_v4()], uuids);
+            let gen = SeededUuidGenerator::new(43);
+            assert_ne!(gen.generate_v4(), uuids[0]);
+        }
+    }
+
+    mod sequential_uuid_generator {
+        use super::*;
+
+        // Tests
+
+        #[test]
+        fn counter() {
+            let gen = SequentialUuidGenerator::new();
+            let uuid = gen.clone().generate_v4();
+            assert_eq!(uuid.to_string(), "00000000-0000-0000-0000-000000000001");
+            let uuid = gen.generate_v4();
+            assert_eq!(uuid.to_string(), "00000000-0000-0000-0000-000000000002");
+            assert_eq!(gen.count(), 2);
+        }
+
+        #[test]
+        #[should_panic]
+        fn exhausted() {
+            let gen = SequentialUuidGenerator::replaying(vec![Uuid::nil()]);
+            assert_eq!(gen.generate_v4(), Uuid::nil());
+            gen.generate_v4();
+        }
+    }
+}